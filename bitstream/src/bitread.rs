@@ -1,6 +1,26 @@
 //! Bitstream reader functionality.
 
 use crate::byteread::*;
+use crate::codebook::CodebookMode;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Bitstream reading errors.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BitError {
+    /// The analyzed bitstream does not contain a valid code.
+    InvalidCode,
+}
+
+impl std::error::Error for BitError {}
+
+impl fmt::Display for BitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BitError::InvalidCode => write!(f, "Invalid Code"),
+        }
+    }
+}
 
 /// Used to interact with a sequence of 64 bits, taking into account the
 /// relative endianness.
@@ -11,9 +31,9 @@ pub trait BitReadEndian {
     /// Peeks n bits from the cache.
     fn peek_val(&mut self, n: usize) -> u64;
     /// Merges two sequences of bits together.
-    fn merge_val(msp: u64, lsp: u64, msb: usize, lsb: usize) -> u64;
+    fn merge_val(&self, msp: u64, lsp: u64, msb: usize, lsb: usize) -> u64;
     /// Builds a new cache.
-    fn build_cache(cache: u64, refill: u64, cache_size: usize) -> u64;
+    fn build_cache(&self, cache: u64, refill: u64, cache_size: usize) -> u64;
     /// Removes n bits from the cache.
     fn skip_rem(&mut self, n: usize);
 }
@@ -49,6 +69,13 @@ pub trait BitReadInternal: BitReadEndian + BitReadFill {
     }
 }
 
+/// Used to limit the data a bitreader can read.
+pub trait BitReadLimit {
+    /// Limits the readable data to the first `end` bits of an internal
+    /// buffer, the bits past them are read as zeroes.
+    fn set_end(&mut self, end: usize);
+}
+
 /// Used to define a bitreader.
 pub trait BitRead<'a>: BitReadInternal + Copy {
     /// Creates a new bitreader with an internal buffer associated to it.
@@ -88,7 +115,9 @@ pub trait BitRead<'a>: BitReadInternal + Copy {
             self.refill64();
         }
 
-        Self::merge_val(self.get_val(n), ret, left, n)
+        let val = self.get_val(n);
+
+        self.merge_val(val, ret, left, n)
     }
 
     /// Returns n bits from the internal buffer as a 32-bit sequence.
@@ -105,9 +134,88 @@ pub trait BitRead<'a>: BitReadInternal + Copy {
         self.get_val(n) as u32
     }
 
+    /// Unpacks `out.len()` samples of `depth` bits each from the
+    /// internal buffer.
+    ///
+    /// Depths greater than 16 are rejected without consuming any bit.
+    /// The samples are extracted from the cache in runs, refilling it
+    /// only between runs.
+    #[inline]
+    fn read_samples_u16(&mut self, out: &mut [u16], depth: usize) -> Result<(), BitError> {
+        if depth > 16 {
+            return Err(BitError::InvalidCode);
+        }
+
+        if depth == 0 {
+            out.fill(0);
+            return Ok(());
+        }
+
+        let mut pos = 0;
+        while pos < out.len() {
+            if self.left() < depth {
+                self.refill32();
+            }
+
+            let count = (self.left() / depth).clamp(1, out.len() - pos);
+            for sample in out[pos..pos + count].iter_mut() {
+                *sample = self.get_val(depth) as u16;
+            }
+            pos += count;
+        }
+
+        Ok(())
+    }
+
+    /// Reads an unsigned Exp-Golomb code.
+    #[inline]
+    fn read_ue(&mut self) -> Result<u32, BitError> {
+        let val = read_exp_golomb(self)?;
+
+        u32::try_from(val).map_err(|_| BitError::InvalidCode)
+    }
+
+    /// Reads a signed Exp-Golomb code.
+    #[inline]
+    fn read_se(&mut self) -> Result<i32, BitError> {
+        let val = read_exp_golomb(self)?;
+        let val = if val & 1 != 0 {
+            ((val + 1) >> 1) as i64
+        } else {
+            -((val >> 1) as i64)
+        };
+
+        i32::try_from(val).map_err(|_| BitError::InvalidCode)
+    }
+
+    /// Reads a Rice code with parameter `k`: the quotient in unary form,
+    /// as a run of zeroes terminated by a one, followed by the `k`-bit
+    /// remainder.
+    ///
+    /// Parameters above 32, runs that overflow a `u32` value or
+    /// the internal buffer are rejected.
+    #[inline]
+    fn read_rice(&mut self, k: u8) -> Result<u32, BitError> {
+        if k > 32 {
+            return Err(BitError::InvalidCode);
+        }
+
+        let max_run = u32::MAX.checked_shr(k as u32).unwrap_or(0) as usize;
+        let mut q = 0;
+
+        while !self.get_bit() {
+            q += 1;
+            if q > max_run || self.available() == 0 {
+                return Err(BitError::InvalidCode);
+            }
+        }
+
+        Ok((((q as u64) << k) as u32) | self.get_bits_32(k as usize))
+    }
+
     /// Peeks the next bit present in the internal buffer.
     #[inline]
-    fn peek_bit(&mut self) -> bool {
+    fn peek_bit(&self) -> bool {
         let mut tmp = *self;
 
         tmp.get_bit()
@@ -115,7 +223,7 @@ pub trait BitRead<'a>: BitReadInternal + Copy {
 
     /// Peeks the next 32-bit sequence present in the internal buffer.
     #[inline]
-    fn peek_bits_32(&mut self, n: usize) -> u32 {
+    fn peek_bits_32(&self, n: usize) -> u32 {
         let mut tmp = *self;
 
         tmp.get_bits_32(n)
@@ -136,6 +244,58 @@ pub trait BitRead<'a>: BitReadInternal + Copy {
 
         self.skip_bits(left);
     }
+
+    /// Tells the number of bits left before the next byte boundary.
+    #[inline]
+    fn bits_to_align(&self) -> usize {
+        (8 - self.consumed() % 8) % 8
+    }
+
+    /// Reads the bits left before the next byte boundary and tells
+    /// whether they are all zeroes.
+    #[inline]
+    fn read_alignment_zeros(&mut self) -> Result<bool, BitError> {
+        let n = self.bits_to_align();
+        if self.available() < n {
+            return Err(BitError::InvalidCode);
+        }
+
+        Ok(self.get_bits_32(n) == 0)
+    }
+
+    /// Returns a reader limited to the next `nbits` bits and advances
+    /// this reader past them.
+    ///
+    /// The sub-reader reads zeroes past its limit.
+    fn sub_reader(&mut self, nbits: usize) -> Result<Self, BitError>
+    where
+        Self: BitReadLimit,
+    {
+        if self.available() < nbits {
+            return Err(BitError::InvalidCode);
+        }
+
+        let mut sub = *self;
+        sub.set_end(self.consumed() + nbits);
+        self.skip_bits(nbits);
+
+        Ok(sub)
+    }
+}
+
+// Reads an Exp-Golomb code of up to 33 significant bits.
+#[inline]
+fn read_exp_golomb<'a, R: BitRead<'a>>(reader: &mut R) -> Result<u64, BitError> {
+    let mut zeros = 0;
+
+    while !reader.get_bit() {
+        zeros += 1;
+        if zeros > 32 {
+            return Err(BitError::InvalidCode);
+        }
+    }
+
+    Ok(((1 << zeros) | reader.get_bits_64(zeros)) - 1)
 }
 
 #[doc(hidden)]
@@ -156,6 +316,7 @@ macro_rules! endian_reader {
             index : usize,
             cache : u64,
             left : usize,
+            end : usize,
         }
         impl <'a> BitReadInternal for $name<'a> {
             #[inline]
@@ -169,10 +330,11 @@ macro_rules! endian_reader {
                 }
                 let val = self.fill32();
 
-                self.cache  = Self::build_cache(self.cache, val, self.left);
+                self.cache  = self.build_cache(self.cache, val, self.left);
 
                 self.index += 4;
                 self.left  += 32;
+                self.clip();
             }
             #[inline]
             fn refill64(&mut self) -> () {
@@ -183,6 +345,15 @@ macro_rules! endian_reader {
                 self.cache  = self.fill64();
                 self.index += 8;
                 self.left   = 64;
+                self.clip();
+            }
+        }
+
+        impl <'a> BitReadLimit for $name<'a> {
+            #[inline]
+            fn set_end(&mut self, end: usize) {
+                self.end = end;
+                self.clip();
             }
         }
 
@@ -192,7 +363,8 @@ macro_rules! endian_reader {
                     buffer,
                     index: 0,
                     cache: 0,
-                    left: 0
+                    left: 0,
+                    end: buffer.len() * 8,
                 };
 
                 reader.refill64();
@@ -205,7 +377,7 @@ macro_rules! endian_reader {
 
             #[inline]
             fn available(&self) -> usize {
-                (self.buffer.len() - self.index) * 8 + self.left
+                self.end.saturating_sub(self.consumed())
             }
 
             #[inline]
@@ -248,14 +420,25 @@ macro_rules! little_endian_reader {
                 self.left = self.left.saturating_sub(n);
             }
             #[inline]
-            fn merge_val(msp:u64, lsp:u64, msb:usize, _:usize) -> u64 {
+            fn merge_val(&self, msp:u64, lsp:u64, msb:usize, _:usize) -> u64 {
                 msp << msb | lsp
             }
             #[inline]
-            fn build_cache(cache:u64, refill:u64, cache_size:usize) -> u64 {
+            fn build_cache(&self, cache:u64, refill:u64, cache_size:usize) -> u64 {
                 cache | refill << cache_size
             }
         }
+
+        impl <'a> $name<'a> {
+            // Zeroes the cached bits past the end of the readable data.
+            #[inline]
+            fn clip(&mut self) {
+                let valid = self.end.saturating_sub(self.index * 8 - self.left);
+                if valid < self.left {
+                    self.cache &= (1u64 << valid) - 1;
+                }
+            }
+        }
     }
 }
 
@@ -293,14 +476,25 @@ macro_rules! big_endian_reader {
                 self.left = self.left.saturating_sub(n);
             }
             #[inline]
-            fn merge_val(msp:u64, lsp:u64, _:usize, lsb:usize) -> u64 {
+            fn merge_val(&self, msp:u64, lsp:u64, _:usize, lsb:usize) -> u64 {
                 msp | lsp.checked_shl(lsb as u32).unwrap_or(0)
             }
             #[inline]
-            fn build_cache(cache:u64, refill:u64, cache_size:usize) -> u64 {
+            fn build_cache(&self, cache:u64, refill:u64, cache_size:usize) -> u64 {
                 cache | refill << (32 - cache_size)
             }
         }
+
+        impl <'a> $name<'a> {
+            // Zeroes the cached bits past the end of the readable data.
+            #[inline]
+            fn clip(&mut self) {
+                let valid = self.end.saturating_sub(self.index * 8 - self.left);
+                if valid < self.left {
+                    self.cache &= !(u64::MAX >> valid);
+                }
+            }
+        }
     }
 }
 
@@ -321,10 +515,383 @@ impl<'a> BitReadFill for BitReadBE<'a> {
     }
 }
 
+/// A bitreader whose endianness is selected at runtime.
+#[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum AnyBitReader<'a> {
+    /// Little-endian reader, reading the least significant bit first.
+    LE(BitReadLE<'a>),
+    /// Big-endian reader, reading the most significant bit first.
+    BE(BitReadBE<'a>),
+}
+
+macro_rules! any_reader_dispatch {
+    ($reader:expr, $r:ident => $call:expr) => {
+        match $reader {
+            AnyBitReader::LE($r) => $call,
+            AnyBitReader::BE($r) => $call,
+        }
+    };
+}
+
+impl<'a> AnyBitReader<'a> {
+    /// Creates the bitreader matching the bit order of `mode`.
+    pub fn new(buffer: &'a [u8], mode: CodebookMode) -> Self {
+        match mode {
+            CodebookMode::LSB => AnyBitReader::LE(BitReadLE::new(buffer)),
+            CodebookMode::MSB => AnyBitReader::BE(BitReadBE::new(buffer)),
+        }
+    }
+
+    /// Tells the number of bits read from the internal buffer.
+    #[inline]
+    pub fn consumed(&self) -> usize {
+        any_reader_dispatch!(self, r => r.consumed())
+    }
+
+    /// Tells the number of bits still available in the internal buffer.
+    #[inline]
+    pub fn available(&self) -> usize {
+        any_reader_dispatch!(self, r => r.available())
+    }
+
+    /// Discard a certain number of bits from the internal buffer.
+    #[inline]
+    pub fn skip_bits(&mut self, size: usize) {
+        any_reader_dispatch!(self, r => r.skip_bits(size))
+    }
+
+    /// Returns a single bit from the internal buffer.
+    #[inline]
+    pub fn get_bit(&mut self) -> bool {
+        any_reader_dispatch!(self, r => r.get_bit())
+    }
+
+    /// Returns n bits from the internal buffer as a 32-bit sequence.
+    #[inline]
+    pub fn get_bits_32(&mut self, n: usize) -> u32 {
+        any_reader_dispatch!(self, r => r.get_bits_32(n))
+    }
+
+    /// Returns n bits from the internal buffer as a 64-bit sequence.
+    #[inline]
+    pub fn get_bits_64(&mut self, n: usize) -> u64 {
+        any_reader_dispatch!(self, r => r.get_bits_64(n))
+    }
+
+    /// Peeks the next bit present in the internal buffer.
+    #[inline]
+    pub fn peek_bit(&self) -> bool {
+        any_reader_dispatch!(self, r => r.peek_bit())
+    }
+
+    /// Peeks the next 32-bit sequence present in the internal buffer.
+    #[inline]
+    pub fn peek_bits_32(&self, n: usize) -> u32 {
+        any_reader_dispatch!(self, r => r.peek_bits_32(n))
+    }
+
+    /// Peeks the next 64-bit sequence present in the internal buffer.
+    #[inline]
+    pub fn peek_bits_64(&self, n: usize) -> u64 {
+        any_reader_dispatch!(self, r => r.peek_bits_64(n))
+    }
+
+    /// Aligns the bits present in the internal buffer.
+    #[inline]
+    pub fn align_bits(&mut self) {
+        any_reader_dispatch!(self, r => r.align_bits())
+    }
+
+    /// Tells the number of bits left before the next byte boundary.
+    #[inline]
+    pub fn bits_to_align(&self) -> usize {
+        any_reader_dispatch!(self, r => r.bits_to_align())
+    }
+
+    /// Reads an unsigned Exp-Golomb code.
+    #[inline]
+    pub fn read_ue(&mut self) -> Result<u32, BitError> {
+        any_reader_dispatch!(self, r => r.read_ue())
+    }
+
+    /// Reads a signed Exp-Golomb code.
+    #[inline]
+    pub fn read_se(&mut self) -> Result<i32, BitError> {
+        any_reader_dispatch!(self, r => r.read_se())
+    }
+
+    /// Reads a Rice code with parameter `k`.
+    #[inline]
+    pub fn read_rice(&mut self, k: u8) -> Result<u32, BitError> {
+        any_reader_dispatch!(self, r => r.read_rice(k))
+    }
+}
+
+/// Byte or bit ordering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    /// Least significant first.
+    Little,
+    /// Most significant first.
+    Big,
+}
+
+/// A reader with independent byte and bit orders.
+///
+/// The bytes are grouped in 32-bit words according to the byte order,
+/// then the bits of each word are read according to the bit order.
+#[derive(Debug, Clone, Copy)]
+pub struct BitReadMixed<'a> {
+    buffer: &'a [u8], // read buffer, 8-bytes padded
+    index: usize,
+    cache: u64,
+    left: usize,
+    end: usize,
+    byte_order: Endianness,
+    bit_order: Endianness,
+}
+
+impl<'a> BitReadMixed<'a> {
+    /// Creates a new reader with the given byte and bit orders.
+    pub fn new(buffer: &'a [u8], byte_order: Endianness, bit_order: Endianness) -> Self {
+        let mut reader = BitReadMixed {
+            buffer,
+            index: 0,
+            cache: 0,
+            left: 0,
+            end: buffer.len() * 8,
+            byte_order,
+            bit_order,
+        };
+
+        reader.refill64();
+        reader
+    }
+
+    #[inline(always)]
+    fn word(&self, offset: usize) -> u64 {
+        let buf = &self.buffer[self.index + offset..];
+        let word = match self.byte_order {
+            Endianness::Little => get_u32l(buf),
+            Endianness::Big => get_u32b(buf),
+        };
+
+        u64::from(word)
+    }
+
+    // Zeroes the cached bits past the end of the readable data.
+    #[inline]
+    fn clip(&mut self) {
+        let valid = self.end.saturating_sub(self.index * 8 - self.left);
+        if valid < self.left {
+            self.cache &= match self.bit_order {
+                Endianness::Little => (1u64 << valid) - 1,
+                Endianness::Big => !(u64::MAX >> valid),
+            };
+        }
+    }
+}
+
+impl<'a> BitReadEndian for BitReadMixed<'a> {
+    #[inline]
+    fn peek_val(&mut self, n: usize) -> u64 {
+        match self.bit_order {
+            Endianness::Little => {
+                self.cache & 1u64.checked_shl(n as u32).unwrap_or(0).wrapping_sub(1)
+            }
+            Endianness::Big => self.cache.checked_shr(64 - n as u32).unwrap_or(0),
+        }
+    }
+    #[inline]
+    fn merge_val(&self, msp: u64, lsp: u64, msb: usize, lsb: usize) -> u64 {
+        match self.bit_order {
+            Endianness::Little => msp << msb | lsp,
+            Endianness::Big => msp | lsp.checked_shl(lsb as u32).unwrap_or(0),
+        }
+    }
+    #[inline]
+    fn build_cache(&self, cache: u64, refill: u64, cache_size: usize) -> u64 {
+        match self.bit_order {
+            Endianness::Little => cache | refill << cache_size,
+            Endianness::Big => cache | refill << (32 - cache_size),
+        }
+    }
+    #[inline]
+    fn skip_rem(&mut self, n: usize) {
+        self.cache = match self.bit_order {
+            Endianness::Little => self.cache.checked_shr(n as u32).unwrap_or(0),
+            Endianness::Big => self.cache.checked_shl(n as u32).unwrap_or(0),
+        };
+        self.left = self.left.saturating_sub(n);
+    }
+}
+
+impl<'a> BitReadFill for BitReadMixed<'a> {
+    #[inline]
+    fn can_refill(&self) -> bool {
+        self.index + 8 <= self.buffer.len()
+    }
+    #[inline(always)]
+    fn fill32(&self) -> u64 {
+        self.word(0)
+    }
+    #[inline(always)]
+    fn fill64(&self) -> u64 {
+        match self.bit_order {
+            Endianness::Little => self.word(0) | self.word(4) << 32,
+            Endianness::Big => self.word(0) << 32 | self.word(4),
+        }
+    }
+}
+
+impl<'a> BitReadInternal for BitReadMixed<'a> {
+    #[inline]
+    fn left(&self) -> usize {
+        self.left
+    }
+    #[inline]
+    fn refill32(&mut self) {
+        if !self.can_refill() {
+            return;
+        }
+        let val = self.fill32();
+
+        self.cache = self.build_cache(self.cache, val, self.left);
+
+        self.index += 4;
+        self.left += 32;
+        self.clip();
+    }
+    #[inline]
+    fn refill64(&mut self) {
+        if !self.can_refill() {
+            return;
+        }
+
+        self.cache = self.fill64();
+        self.index += 8;
+        self.left = 64;
+        self.clip();
+    }
+}
+
+impl<'a> BitReadLimit for BitReadMixed<'a> {
+    #[inline]
+    fn set_end(&mut self, end: usize) {
+        self.end = end;
+        self.clip();
+    }
+}
+
+impl<'a> BitRead<'a> for BitReadMixed<'a> {
+    /// Creates a new reader with big-endian byte and bit orders.
+    fn new(buffer: &'a [u8]) -> Self {
+        BitReadMixed::new(buffer, Endianness::Big, Endianness::Big)
+    }
+    #[inline]
+    fn consumed(&self) -> usize {
+        self.index * 8 - self.left
+    }
+    #[inline]
+    fn available(&self) -> usize {
+        self.end.saturating_sub(self.consumed())
+    }
+    #[inline]
+    fn skip_bits(&mut self, mut n: usize) {
+        if self.left < n {
+            n -= self.left;
+            if n > 64 {
+                let skip = n / 8;
+
+                n -= skip * 8;
+                self.index += skip;
+            }
+            self.skip_rem(n);
+            self.refill64();
+        }
+
+        self.skip_rem(n);
+    }
+}
+
+/// A field read through a `TracingBitReader`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldRecord {
+    /// Name of the field.
+    pub name: String,
+    /// Position of the first bit of the field.
+    pub start_bit: usize,
+    /// Size of the field in bits.
+    pub nbits: usize,
+    /// Value read.
+    pub value: u64,
+}
+
+/// Bitreader wrapper recording the named fields read through it.
+///
+/// Meant to help debugging parsers.
+#[derive(Debug, Clone)]
+pub struct TracingBitReader<'a, B: BitRead<'a>> {
+    reader: B,
+    trace: Vec<FieldRecord>,
+    _buffer: PhantomData<&'a [u8]>,
+}
+
+impl<'a, B: BitRead<'a>> TracingBitReader<'a, B> {
+    /// Wraps a bitreader.
+    pub fn new(reader: B) -> Self {
+        TracingBitReader {
+            reader,
+            trace: Vec::new(),
+            _buffer: PhantomData,
+        }
+    }
+
+    /// Reads a field of `n` bits, up to 64, and records it under `name`.
+    pub fn get_bits_named(&mut self, name: &str, n: usize) -> u64 {
+        let start_bit = self.reader.consumed();
+        let value = self.reader.get_bits_64(n);
+
+        self.trace.push(FieldRecord {
+            name: name.to_owned(),
+            start_bit,
+            nbits: n,
+            value,
+        });
+
+        value
+    }
+
+    /// Returns the fields recorded so far.
+    pub fn trace(&self) -> &[FieldRecord] {
+        &self.trace
+    }
+
+    /// Returns the wrapped reader, to read data without recording it.
+    pub fn reader(&mut self) -> &mut B {
+        &mut self.reader
+    }
+
+    /// Unwraps the reader, dropping the recorded fields.
+    pub fn into_inner(self) -> B {
+        self.reader
+    }
+}
+
 #[cfg(test)]
 mod test {
     pub const CHECKBOARD0101: [u8; 128] = [0b01010101; 128];
     pub const CHECKBOARD0011: [u8; 128] = [0b00110011; 128];
+    pub const PACKED10: [u8; 128] = {
+        let mut buf = [0u8; 128];
+        let mut i = 0;
+        while i < buf.len() {
+            buf[i] = (i as u8).wrapping_mul(37) ^ 0x5a;
+            i += 1;
+        }
+        buf
+    };
 
     mod le {
         use super::super::*;
@@ -377,6 +944,7 @@ mod test {
                 index: 0,
                 cache: 0,
                 left: 0,
+                end: CHECKBOARD0101.len() * 8,
             };
 
             assert_eq!(reader.peek_bits_64(1), 1);
@@ -394,6 +962,7 @@ mod test {
                 index: 0,
                 cache: 0,
                 left: 0,
+                end: CHECKBOARD0101.len() * 8,
             };
 
             assert!(reader.get_bits_32(1) == 1);
@@ -411,7 +980,7 @@ mod test {
         #[test]
         fn peek_bits_32() {
             let b = &CHECKBOARD0101;
-            let mut reader = BitReadLE::new(b);
+            let reader = BitReadLE::new(b);
 
             assert!(reader.peek_bits_32(1) == 1);
             assert!(reader.peek_bits_32(1) == 1);
@@ -446,6 +1015,47 @@ mod test {
             assert!(reader.get_bits_64(4) == 3);
         }
 
+        #[test]
+        fn read_samples_u16() {
+            let b = &PACKED10;
+            let mut reader = BitReadLE::new(b);
+            let mut ref_reader = BitReadLE::new(b);
+            let mut samples = [0u16; 80];
+
+            assert_eq!(reader.read_samples_u16(&mut samples, 10), Ok(()));
+            for s in samples {
+                assert_eq!(s as u32, ref_reader.get_bits_32(10));
+            }
+            assert_eq!(reader.consumed(), 800);
+        }
+
+        #[test]
+        fn read_samples_u16_depths() {
+            let b = &PACKED10;
+
+            for depth in [1, 7, 13, 16] {
+                let mut reader = BitReadLE::new(b);
+                let mut ref_reader = BitReadLE::new(b);
+                let mut samples = [0u16; 37];
+
+                reader.skip_bits(3);
+                ref_reader.skip_bits(3);
+                assert_eq!(reader.read_samples_u16(&mut samples, depth), Ok(()));
+                for s in samples {
+                    assert_eq!(s as u32, ref_reader.get_bits_32(depth));
+                }
+                assert_eq!(reader.consumed(), ref_reader.consumed());
+            }
+
+            let mut reader = BitReadLE::new(b);
+            let mut samples = [0u16; 4];
+            assert_eq!(
+                reader.read_samples_u16(&mut samples, 17),
+                Err(BitError::InvalidCode)
+            );
+            assert_eq!(reader.consumed(), 0);
+        }
+
         #[test]
         fn overread() {
             let b = &CHECKBOARD0011;
@@ -532,7 +1142,7 @@ mod test {
         #[test]
         fn peek_bits_32() {
             let b = &CHECKBOARD0101;
-            let mut reader = BitReadBE::new(b);
+            let reader = BitReadBE::new(b);
 
             assert!(reader.peek_bits_32(1) == 0);
             assert!(reader.peek_bits_32(1) == 0);
@@ -540,6 +1150,25 @@ mod test {
             assert!(reader.peek_bits_32(2) == 1);
         }
 
+        #[test]
+        fn peek_shared() {
+            fn peek(reader: &BitReadBE) -> (bool, u32, u64) {
+                (
+                    reader.peek_bit(),
+                    reader.peek_bits_32(8),
+                    reader.peek_bits_64(40),
+                )
+            }
+
+            let b = &CHECKBOARD0101;
+            let mut reader = BitReadBE::new(b);
+
+            reader.skip_bits(1);
+            assert_eq!(peek(&reader), (true, 0xaa, 0xaa_aaaa_aaaa));
+            assert_eq!(peek(&reader), (true, 0xaa, 0xaa_aaaa_aaaa));
+            assert_eq!(reader.available(), b.len() * 8 - 1);
+        }
+
         #[test]
         fn skip_bits() {
             let b = &CHECKBOARD0101;
@@ -567,6 +1196,20 @@ mod test {
             assert!(reader.get_bits_64(4) == 3);
         }
 
+        #[test]
+        fn read_samples_u16() {
+            let b = &PACKED10;
+            let mut reader = BitReadBE::new(b);
+            let mut ref_reader = BitReadBE::new(b);
+            let mut samples = [0u16; 80];
+
+            assert_eq!(reader.read_samples_u16(&mut samples, 10), Ok(()));
+            for s in samples {
+                assert_eq!(s as u32, ref_reader.get_bits_32(10));
+            }
+            assert_eq!(reader.consumed(), 800);
+        }
+
         #[test]
         fn overread() {
             let b = &CHECKBOARD0011;
@@ -575,5 +1218,238 @@ mod test {
             reader.skip_bits(128 * 8 + 2);
             reader.get_bits_64(6);
         }
+
+        #[test]
+        fn read_exp_golomb() {
+            let mut b = [0u8; 16];
+            b[..3].copy_from_slice(&[0b1010_0110, 0b0100_0010, 0b1010_0000]);
+            let mut reader = BitReadBE::new(&b);
+
+            for val in 0..5 {
+                assert_eq!(reader.read_ue(), Ok(val));
+            }
+            assert_eq!(reader.read_se(), Ok(1));
+            assert_eq!(reader.read_ue(), Err(BitError::InvalidCode));
+        }
+
+        #[test]
+        fn read_rice() {
+            let mut b = [0u8; 16];
+            b[0] = 0b1111_0001;
+            let mut reader = BitReadBE::new(&b);
+
+            assert_eq!(reader.read_rice(0), Ok(0));
+            assert_eq!(reader.read_rice(2), Ok(3));
+            assert_eq!(reader.read_rice(0), Ok(3));
+            assert_eq!(reader.read_rice(31), Err(BitError::InvalidCode));
+            assert_eq!(reader.read_rice(0), Err(BitError::InvalidCode));
+
+            let b = [0x80u8; 16];
+            let mut reader = BitReadBE::new(&b);
+            assert_eq!(reader.read_rice(33), Err(BitError::InvalidCode));
+            assert_eq!(reader.consumed(), 0);
+            assert_eq!(reader.read_rice(32), Ok(0x0101_0101));
+        }
+
+        #[test]
+        fn alignment() {
+            let b = [0b1010_0000, 0b0100_0001, 0, 0, 0, 0, 0, 0, 0, 0];
+            let mut reader = BitReadBE::new(&b);
+            assert_eq!(reader.bits_to_align(), 0);
+            assert_eq!(reader.read_alignment_zeros(), Ok(true));
+
+            reader.skip_bits(3);
+            assert_eq!(reader.bits_to_align(), 5);
+            assert_eq!(reader.read_alignment_zeros(), Ok(true));
+            assert_eq!(reader.consumed(), 8);
+
+            reader.skip_bits(1);
+            assert_eq!(reader.bits_to_align(), 7);
+            assert_eq!(reader.read_alignment_zeros(), Ok(false));
+            assert_eq!(reader.bits_to_align(), 0);
+
+            reader.skip_bits(7);
+            assert_eq!(reader.bits_to_align(), 1);
+        }
+
+        #[test]
+        fn tracing() {
+            let b = [0b1010_1100, 0b0011_0101, 0, 0, 0, 0, 0, 0, 0, 0];
+            let mut reader = TracingBitReader::new(BitReadBE::new(&b));
+
+            assert_eq!(reader.get_bits_named("marker", 1), 1);
+            reader.reader().skip_bits(3);
+            assert_eq!(reader.get_bits_named("size", 6), 0b11_0000);
+            assert_eq!(reader.get_bits_named("flags", 6), 0b11_0101);
+
+            let field = |name: &str, start_bit, nbits, value| FieldRecord {
+                name: name.to_owned(),
+                start_bit,
+                nbits,
+                value,
+            };
+            assert_eq!(
+                reader.trace(),
+                [
+                    field("marker", 0, 1, 1),
+                    field("size", 4, 6, 0b11_0000),
+                    field("flags", 10, 6, 0b11_0101),
+                ]
+            );
+        }
+
+        #[test]
+        fn sub_reader() {
+            let b = [0b1010_1100, 0b0011_0101, 0xff, 0, 0, 0, 0, 0, 0, 0];
+            let mut reader = BitReadBE::new(&b);
+            reader.skip_bits(2);
+
+            let mut sub = reader.sub_reader(12).unwrap();
+            assert_eq!(sub.available(), 12);
+            assert_eq!(reader.consumed(), 14);
+
+            assert_eq!(sub.get_bits_32(12), 0b1011_0000_1101);
+            assert_eq!(sub.available(), 0);
+            assert!(sub.sub_reader(1).is_err());
+
+            assert_eq!(reader.get_bits_32(4), 0b0111);
+            assert!(reader.sub_reader(b.len() * 8).is_err());
+        }
+
+        #[test]
+        fn sub_reader_overread() {
+            let b = [0b1010_1100, 0b0011_0101, 0xff, 0xff, 0, 0, 0, 0, 0, 0];
+            let mut reader = BitReadBE::new(&b);
+
+            let mut sub = reader.sub_reader(12).unwrap();
+            assert_eq!(sub.peek_bits_32(16), 0b1010_1100_0011_0000);
+            sub.skip_bits(12);
+            assert_eq!(sub.get_bits_32(4), 0);
+            assert_eq!(sub.get_bits_64(40), 0);
+            assert!(!sub.get_bit());
+            assert!(sub.read_ue().is_err());
+            assert!(sub.read_rice(2).is_err());
+
+            let mut sub = reader.sub_reader(20).unwrap();
+            sub.skip_bits(100);
+            assert_eq!(sub.get_bits_32(8), 0);
+
+            let mut mixed = BitReadMixed::new(&b, Endianness::Little, Endianness::Little);
+            let mut sub = mixed.sub_reader(4).unwrap();
+            assert_eq!(sub.get_bits_32(8), 0b1100);
+        }
+    }
+    mod mixed {
+        use super::super::*;
+
+        fn data() -> Vec<u8> {
+            let mut b = (0..64u32).map(|i| (i * 73 + 11) as u8).collect::<Vec<_>>();
+            b.resize(72, 0);
+            b
+        }
+
+        // Reverses the bytes of each 32-bit word.
+        fn swapped(b: &[u8]) -> Vec<u8> {
+            b.chunks(4).flat_map(|w| w.iter().rev().copied()).collect()
+        }
+
+        fn compare<'a, R: BitRead<'a>>(mut mixed: BitReadMixed<'a>, mut reference: R) {
+            for n in [1, 3, 7, 13, 32, 5, 64, 17, 31, 9, 64, 2, 33] {
+                if n > 32 {
+                    assert_eq!(mixed.get_bits_64(n), reference.get_bits_64(n));
+                } else {
+                    assert_eq!(mixed.get_bits_32(n), reference.get_bits_32(n));
+                }
+                assert_eq!(mixed.consumed(), reference.consumed());
+            }
+        }
+
+        #[test]
+        fn endian_helpers() {
+            let b = data();
+            let le = BitReadLE::new(&b);
+            let be = BitReadBE::new(&b);
+            let mixed_le = BitReadMixed::new(&b, Endianness::Big, Endianness::Little);
+            let mixed_be = BitReadMixed::new(&b, Endianness::Little, Endianness::Big);
+
+            for (msb, lsb) in [(3, 5), (17, 40), (0, 1)] {
+                let (msp, lsp) = (0x15, 0x2a);
+                assert_eq!(
+                    mixed_le.merge_val(msp, lsp, msb, lsb),
+                    le.merge_val(msp, lsp, msb, lsb)
+                );
+                assert_eq!(
+                    mixed_be.merge_val(msp, lsp, msb, lsb),
+                    be.merge_val(msp, lsp, msb, lsb)
+                );
+            }
+            assert_eq!(
+                mixed_le.build_cache(0x1f, 0xabcd, 12),
+                le.build_cache(0x1f, 0xabcd, 12)
+            );
+            assert_eq!(
+                mixed_be.build_cache(0x1f << 52, 0xabcd, 12),
+                be.build_cache(0x1f << 52, 0xabcd, 12)
+            );
+        }
+
+        #[test]
+        fn le_bytes_be_bits() {
+            let b = data();
+            let s = swapped(&b);
+            let mut reader = BitReadMixed::new(&b, Endianness::Little, Endianness::Big);
+
+            assert_eq!(reader.get_bits_32(8), b[3] as u32);
+            assert_eq!(reader.peek_bits_32(8), b[2] as u32);
+
+            let reader = BitReadMixed::new(&b, Endianness::Little, Endianness::Big);
+            compare(reader, BitReadBE::new(&s));
+        }
+
+        #[test]
+        fn be_bytes_le_bits() {
+            let b = data();
+            let s = swapped(&b);
+
+            let reader = BitReadMixed::new(&b, Endianness::Big, Endianness::Little);
+            compare(reader, BitReadLE::new(&s));
+        }
+
+        #[test]
+        fn same_orders() {
+            let b = data();
+
+            let reader = BitReadMixed::new(&b, Endianness::Big, Endianness::Big);
+            compare(reader, BitReadBE::new(&b));
+            let reader = BitReadMixed::new(&b, Endianness::Little, Endianness::Little);
+            compare(reader, BitReadLE::new(&b));
+        }
+    }
+    mod any {
+        use super::super::*;
+
+        #[test]
+        fn delegate() {
+            let b = [0b1010_0011u8, 0xff, 0x0f, 0x5a, 0, 0, 0, 0, 0, 0, 0, 0];
+
+            for mode in [CodebookMode::LSB, CodebookMode::MSB] {
+                let mut any = AnyBitReader::new(&b, mode);
+                let mut le = BitReadLE::new(&b);
+                let mut be = BitReadBE::new(&b);
+
+                for n in [3, 1, 12, 5, 7] {
+                    let expected = match mode {
+                        CodebookMode::LSB => le.get_bits_32(n),
+                        CodebookMode::MSB => be.get_bits_32(n),
+                    };
+                    assert_eq!(any.peek_bits_32(n), expected);
+                    assert_eq!(any.get_bits_32(n), expected);
+                }
+                assert_eq!(any.consumed(), 28);
+                assert_eq!(any.available(), b.len() * 8 - 28);
+                any.align_bits();
+                assert_eq!(any.consumed(), 32);
+            }
+        }
     }
 }
@@ -0,0 +1,302 @@
+//! Bitstream writing functionality.
+
+use std::io::{Result, Write};
+
+/// Used to define a bitwriter.
+pub trait BitWrite {
+    /// Tells the number of bits written so far.
+    fn written(&self) -> usize;
+
+    /// Writes the `n` least significant bits of `val`.
+    ///
+    /// `n` must not be greater than 32.
+    fn put_bits_32(&mut self, val: u32, n: usize) -> Result<()>;
+
+    /// Writes a single bit.
+    #[inline]
+    fn put_bit(&mut self, bit: bool) -> Result<()> {
+        self.put_bits_32(bit as u32, 1)
+    }
+
+    /// Writes the `n` least significant bits of `val`.
+    ///
+    /// `n` must not be greater than 64.
+    #[inline]
+    fn put_bits_64(&mut self, val: u64, n: usize) -> Result<()> {
+        if n > 32 {
+            self.put_bits_32((val >> 32) as u32, n - 32)?;
+            self.put_bits_32(val as u32, 32)
+        } else {
+            self.put_bits_32(val as u32, n)
+        }
+    }
+
+    /// Writes a Rice code with parameter `k`: the quotient in unary form,
+    /// as a run of zeroes terminated by a one, followed by the `k`-bit
+    /// remainder.
+    ///
+    /// `k` must not be greater than 32.
+    #[inline]
+    fn put_rice(&mut self, val: u32, k: u8) -> Result<()> {
+        let mut q = (val as u64 >> k) as usize;
+
+        while q >= 32 {
+            self.put_bits_32(0, 32)?;
+            q -= 32;
+        }
+        self.put_bits_32(1, q + 1)?;
+        self.put_bits_32(val, k as usize)
+    }
+
+    /// Writes an unsigned Exp-Golomb code.
+    #[inline]
+    fn put_ue(&mut self, val: u32) -> Result<()> {
+        put_exp_golomb(self, val as u64)
+    }
+
+    /// Writes a signed Exp-Golomb code.
+    #[inline]
+    fn put_se(&mut self, val: i32) -> Result<()> {
+        let val = val as i64;
+        let val = if val > 0 { 2 * val - 1 } else { -2 * val };
+
+        put_exp_golomb(self, val as u64)
+    }
+}
+
+// Writes an Exp-Golomb code of up to 33 significant bits.
+#[inline]
+fn put_exp_golomb<W: BitWrite + ?Sized>(writer: &mut W, val: u64) -> Result<()> {
+    let val = val + 1;
+    let len = 64 - val.leading_zeros() as usize;
+
+    writer.put_bits_32(0, len - 1)?;
+    writer.put_bits_64(val, len)
+}
+
+/// Big-endian bitwriter emitting complete bytes to a `Write` sink.
+///
+/// The bits of the final partial byte are only written by `flush`.
+pub struct BitWriterStream<W: Write> {
+    inner: W,
+    cache: u64,
+    bits: usize,
+    written: usize,
+}
+
+impl<W: Write> BitWriterStream<W> {
+    /// Creates a new bitwriter over a `Write` sink.
+    pub fn new(inner: W) -> Self {
+        BitWriterStream {
+            inner,
+            cache: 0,
+            bits: 0,
+            written: 0,
+        }
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwraps the bitwriter, returning the underlying writer.
+    ///
+    /// Note that any bits not flushed yet are lost.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn write_bytes(&mut self) -> Result<()> {
+        let nbytes = self.bits / 8;
+        if nbytes == 0 {
+            return Ok(());
+        }
+
+        let bytes = (self.cache << (64 - self.bits)).to_be_bytes();
+        self.inner.write_all(&bytes[..nbytes])?;
+        self.bits -= nbytes * 8;
+        self.cache &= (1 << self.bits) - 1;
+
+        Ok(())
+    }
+
+    /// Writes all the pending bits, padding the last byte with zeroes,
+    /// and flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.flush_with_padding(false)
+    }
+
+    /// Writes all the pending bits, padding the last byte with `pad_bit`,
+    /// and flushes the underlying writer.
+    pub fn flush_with_padding(&mut self, pad_bit: bool) -> Result<()> {
+        self.write_bytes()?;
+        if self.bits > 0 {
+            let pad = 8 - self.bits;
+            self.cache <<= pad;
+            if pad_bit {
+                self.cache |= (1 << pad) - 1;
+            }
+            self.bits += pad;
+            self.written += pad;
+            self.write_bytes()?;
+        }
+
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> BitWrite for BitWriterStream<W> {
+    fn written(&self) -> usize {
+        self.written
+    }
+
+    #[inline]
+    fn put_bits_32(&mut self, val: u32, n: usize) -> Result<()> {
+        assert!(n <= 32);
+
+        if n == 0 {
+            return Ok(());
+        }
+
+        let mask = (1u64 << n) - 1;
+        self.cache = (self.cache << n) | (val as u64 & mask);
+        self.bits += n;
+        self.written += n;
+
+        if self.bits >= 32 {
+            self.write_bytes()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitread::{BitRead, BitReadBE};
+    use std::io::{self, Cursor};
+
+    #[test]
+    fn put_bits_roundtrip() {
+        let mut bw = BitWriterStream::new(Cursor::new(Vec::new()));
+        let pattern = [(1, 1), (0x5, 3), (0x1ff, 9), (0xdeadbeef, 32), (0, 2)];
+
+        for &(val, n) in pattern.iter() {
+            bw.put_bits_32(val, n).unwrap();
+        }
+        bw.put_bits_64(0x0123_4567_89ab_cdef, 64).unwrap();
+        assert_eq!(bw.written(), 111);
+        bw.flush().unwrap();
+        assert_eq!(bw.written(), 112);
+
+        let mut buf = bw.into_inner().into_inner();
+        assert_eq!(buf.len(), 14);
+
+        // The reader expects 8 bytes of padding.
+        buf.resize(buf.len() + 8, 0);
+        let mut br = BitReadBE::new(&buf);
+        for &(val, n) in pattern.iter() {
+            assert_eq!(br.get_bits_32(n), val);
+        }
+        assert_eq!(br.get_bits_32(32), 0x0123_4567);
+        assert_eq!(br.get_bits_32(32), 0x89ab_cdef);
+        assert_eq!(br.get_bits_32(1), 0);
+    }
+
+    fn write_to_vec<F>(f: F) -> Vec<u8>
+    where
+        F: FnOnce(&mut BitWriterStream<Cursor<Vec<u8>>>) -> io::Result<()>,
+    {
+        let mut bw = BitWriterStream::new(Cursor::new(Vec::new()));
+
+        f(&mut bw).unwrap();
+        bw.flush().unwrap();
+
+        let mut buf = bw.into_inner().into_inner();
+        // The reader expects 8 bytes of padding.
+        buf.resize(buf.len() + 8, 0);
+        buf
+    }
+
+    #[test]
+    fn put_rice() {
+        let values = [0, 1, 2, 3, 7, 31, 32, 100, 1000, 65535];
+
+        for k in [0, 1, 4, 7, 16, 32] {
+            let buf = write_to_vec(|bw| {
+                for &val in values.iter() {
+                    bw.put_rice(val, k)?;
+                }
+                Ok(())
+            });
+
+            let mut br = BitReadBE::new(&buf);
+            for &val in values.iter() {
+                assert_eq!(br.read_rice(k), Ok(val));
+            }
+        }
+    }
+
+    #[test]
+    fn put_exp_golomb() {
+        let values = [0, 1, 2, 3, 4, 7, 8, 255, 65535, u32::MAX - 1, u32::MAX];
+        let signed = [0, 1, -1, 2, -2, 1000, -1000, i32::MAX, i32::MIN];
+
+        let buf = write_to_vec(|bw| {
+            for &val in values.iter() {
+                bw.put_ue(val)?;
+            }
+            for &val in signed.iter() {
+                bw.put_se(val)?;
+            }
+            Ok(())
+        });
+
+        // Smallest codes from the H.264 specification table.
+        assert_eq!(&buf[..2], &[0b1010_0110, 0b0100_0010]);
+
+        let mut br = BitReadBE::new(&buf);
+        for &val in values.iter() {
+            assert_eq!(br.read_ue(), Ok(val));
+        }
+        for &val in signed.iter() {
+            assert_eq!(br.read_se(), Ok(val));
+        }
+    }
+
+    #[test]
+    fn flush_with_padding() {
+        let mut bw = BitWriterStream::new(Vec::new());
+        bw.put_bits_32(0b101, 3).unwrap();
+        bw.flush_with_padding(true).unwrap();
+        assert_eq!(bw.written(), 8);
+        assert_eq!(bw.get_ref(), &[0b1011_1111]);
+
+        let mut bw = BitWriterStream::new(Vec::new());
+        bw.put_bits_32(0b101, 3).unwrap();
+        bw.flush().unwrap();
+        assert_eq!(bw.get_ref(), &[0b1010_0000]);
+    }
+
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("failing"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_error() {
+        let mut bw = BitWriterStream::new(FailingWriter);
+
+        bw.put_bits_32(0x7f, 7).unwrap();
+        assert!(bw.flush().is_err());
+    }
+}
@@ -1,5 +1,7 @@
 //! Bytestream reader functionality.
 
+use std::io::{Error, ErrorKind, Read, Result};
+
 // TODO: arch-specific version
 // TODO: aligned/non-aligned version
 
@@ -141,3 +143,293 @@ pub fn get_f64l(buf: &[u8]) -> f64 {
 pub fn get_f64b(buf: &[u8]) -> f64 {
     f64::from_bits(get_u64b(buf))
 }
+
+macro_rules! get {
+    ($s: ident, $name: ident, $size: expr) => {{
+        let mut buf = [0; $size];
+        $s.read_exact(&mut buf)?;
+        Ok($name(&buf))
+    }};
+}
+
+/// Used to read numbers and strings from a source.
+pub trait ByteRead: Read {
+    /// Reads a byte and returns it as a `u8`.
+    fn get_u8(&mut self) -> Result<u8> {
+        get!(self, get_u8, 1)
+    }
+    /// Reads a byte and returns it as an `i8`.
+    fn get_i8(&mut self) -> Result<i8> {
+        get!(self, get_i8, 1)
+    }
+    /// Reads 2 bytes stored in a little-endian order and returns them
+    /// as an `u16`.
+    fn get_u16l(&mut self) -> Result<u16> {
+        get!(self, get_u16l, 2)
+    }
+    /// Reads 2 bytes stored in a little-endian order and returns them
+    /// as an `i16`.
+    fn get_i16l(&mut self) -> Result<i16> {
+        get!(self, get_i16l, 2)
+    }
+    /// Reads 4 bytes stored in a little-endian order and returns them
+    /// as an `u32`.
+    fn get_u32l(&mut self) -> Result<u32> {
+        get!(self, get_u32l, 4)
+    }
+    /// Reads 4 bytes stored in a little-endian order and returns them
+    /// as an `i32`.
+    fn get_i32l(&mut self) -> Result<i32> {
+        get!(self, get_i32l, 4)
+    }
+    /// Reads 8 bytes stored in a little-endian order and returns them
+    /// as an `u64`.
+    fn get_u64l(&mut self) -> Result<u64> {
+        get!(self, get_u64l, 8)
+    }
+    /// Reads 8 bytes stored in a little-endian order and returns them
+    /// as an `i64`.
+    fn get_i64l(&mut self) -> Result<i64> {
+        get!(self, get_i64l, 8)
+    }
+    /// Reads 4 bytes stored in a little-endian order and returns them
+    /// as a `f32`.
+    fn get_f32l(&mut self) -> Result<f32> {
+        get!(self, get_f32l, 4)
+    }
+    /// Reads 8 bytes stored in a little-endian order and returns them
+    /// as a `f64`.
+    fn get_f64l(&mut self) -> Result<f64> {
+        get!(self, get_f64l, 8)
+    }
+    /// Reads 2 bytes stored in a big-endian order and returns them
+    /// as an `u16`.
+    fn get_u16b(&mut self) -> Result<u16> {
+        get!(self, get_u16b, 2)
+    }
+    /// Reads 2 bytes stored in a big-endian order and returns them
+    /// as an `i16`.
+    fn get_i16b(&mut self) -> Result<i16> {
+        get!(self, get_i16b, 2)
+    }
+    /// Reads 4 bytes stored in a big-endian order and returns them
+    /// as an `u32`.
+    fn get_u32b(&mut self) -> Result<u32> {
+        get!(self, get_u32b, 4)
+    }
+    /// Reads 4 bytes stored in a big-endian order and returns them
+    /// as an `i32`.
+    fn get_i32b(&mut self) -> Result<i32> {
+        get!(self, get_i32b, 4)
+    }
+    /// Reads 8 bytes stored in a big-endian order and returns them
+    /// as an `u64`.
+    fn get_u64b(&mut self) -> Result<u64> {
+        get!(self, get_u64b, 8)
+    }
+    /// Reads 8 bytes stored in a big-endian order and returns them
+    /// as an `i64`.
+    fn get_i64b(&mut self) -> Result<i64> {
+        get!(self, get_i64b, 8)
+    }
+    /// Reads 4 bytes stored in a big-endian order and returns them
+    /// as a `f32`.
+    fn get_f32b(&mut self) -> Result<f32> {
+        get!(self, get_f32b, 4)
+    }
+    /// Reads 8 bytes stored in a big-endian order and returns them
+    /// as a `f64`.
+    fn get_f64b(&mut self) -> Result<f64> {
+        get!(self, get_f64b, 8)
+    }
+    /// Reads up to `max` bytes, stopping after a NUL terminator.
+    ///
+    /// Invalid UTF-8 sequences are replaced.
+    fn get_cstring(&mut self, max: usize) -> Result<String> {
+        let mut buf = Vec::new();
+        while buf.len() < max {
+            match self.get_u8()? {
+                0 => break,
+                b => buf.push(b),
+            }
+        }
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Reads a string prefixed by its length stored in a byte.
+    ///
+    /// Invalid UTF-8 sequences are replaced.
+    fn get_pstring_u8(&mut self) -> Result<String> {
+        let len = self.get_u8()? as usize;
+        get_string(self, len)
+    }
+
+    /// Reads a string prefixed by its length stored in 2 bytes
+    /// in a big-endian order.
+    ///
+    /// Invalid UTF-8 sequences are replaced.
+    fn get_pstring_u16b(&mut self) -> Result<String> {
+        let len = self.get_u16b()? as usize;
+        get_string(self, len)
+    }
+
+    /// Reads an unsigned integer stored in `nbytes` bytes
+    /// in a big-endian order.
+    ///
+    /// `nbytes` must not be greater than 8.
+    fn get_uint_be(&mut self, nbytes: usize) -> Result<u64> {
+        let mut buf = [0; 8];
+        self.read_exact(uint_bytes(&mut buf, nbytes, true)?)?;
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    /// Reads an unsigned integer stored in `nbytes` bytes
+    /// in a little-endian order.
+    ///
+    /// `nbytes` must not be greater than 8.
+    fn get_uint_le(&mut self, nbytes: usize) -> Result<u64> {
+        let mut buf = [0; 8];
+        self.read_exact(uint_bytes(&mut buf, nbytes, false)?)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Reads `magic.len()` bytes and checks that they match `magic`.
+    ///
+    /// The bytes are consumed even if they do not match.
+    fn expect_magic(&mut self, magic: &[u8]) -> Result<()> {
+        let mut buf = vec![0; magic.len()];
+        self.read_exact(&mut buf)?;
+
+        if buf != magic {
+            return Err(Error::new(ErrorKind::InvalidData, "magic mismatch"));
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read + ?Sized> ByteRead for R {}
+
+fn get_string<R: Read + ?Sized>(reader: &mut R, len: usize) -> Result<String> {
+    let mut buf = vec![0; len];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+// Returns the bytes of `buf` holding the least significant `nbytes`.
+fn uint_bytes(buf: &mut [u8; 8], nbytes: usize, be: bool) -> Result<&mut [u8]> {
+    if nbytes > buf.len() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "more than 8 bytes requested",
+        ));
+    }
+
+    if be {
+        Ok(&mut buf[8 - nbytes..])
+    } else {
+        Ok(&mut buf[..nbytes])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    macro_rules! test_read {
+        {$fun: ident, $val: expr, $len: expr} => {
+            #[test]
+            fn $fun() {
+                let mut buf = Cursor::new(vec![1; 17]);
+
+                for _ in 0..$len {
+                    let v = ByteRead::$fun(&mut buf).unwrap();
+                    assert!(v == $val);
+                }
+                match ByteRead::$fun(&mut buf) {
+                    Ok(_) => panic!("read past the end"),
+                    Err(e) => assert!(e.kind() == ErrorKind::UnexpectedEof)
+                }
+            }
+        }
+    }
+
+    mod read {
+        use super::*;
+
+        test_read! { get_u8, 1, 17 }
+        test_read! { get_i8, 1, 17 }
+        test_read! { get_u16l, 257, 8 }
+        test_read! { get_i16l, 257, 8 }
+        test_read! { get_u32l, 16_843_009, 4 }
+        test_read! { get_i32l, 16_843_009, 4 }
+        test_read! { get_u64l, 72_340_172_838_076_673u64, 2 }
+        test_read! { get_i64l, 72_340_172_838_076_673i64, 2 }
+        test_read! { get_u16b, 257, 8 }
+        test_read! { get_u32b, 16_843_009, 4 }
+    }
+
+    #[test]
+    fn get_cstring() {
+        let mut buf = Cursor::new(b"title\0artist\0truncated".to_vec());
+
+        assert_eq!(buf.get_cstring(16).unwrap(), "title");
+        assert_eq!(buf.get_cstring(16).unwrap(), "artist");
+        assert_eq!(buf.get_cstring(5).unwrap(), "trunc");
+        assert_eq!(
+            buf.get_cstring(16).unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn get_pstring() {
+        let mut buf = Cursor::new(b"\x05hello\x00\x03\xffab\x04ab".to_vec());
+
+        assert_eq!(buf.get_pstring_u8().unwrap(), "hello");
+        assert_eq!(buf.get_pstring_u16b().unwrap(), "\u{fffd}ab");
+        assert_eq!(
+            buf.get_pstring_u8().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn get_uint() {
+        let data: Vec<u8> = (1..=16).collect();
+
+        let mut cur = Cursor::new(&data);
+        assert_eq!(cur.get_uint_be(3).unwrap(), 0x01_0203);
+        assert_eq!(cur.get_uint_be(5).unwrap(), 0x04_0506_0708);
+        assert_eq!(cur.get_uint_be(7).unwrap(), 0x09_0a0b_0c0d_0e0f);
+        assert_eq!(cur.get_uint_be(0).unwrap(), 0);
+        assert_eq!(cur.get_uint_be(1).unwrap(), 0x10);
+        assert!(cur.get_uint_be(1).is_err());
+
+        let mut cur = Cursor::new(&data);
+        assert_eq!(cur.get_uint_le(3).unwrap(), 0x03_0201);
+        assert_eq!(cur.get_uint_le(8).unwrap(), 0x0b0a_0908_0706_0504);
+        assert_eq!(
+            cur.get_uint_be(9).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(cur.position(), 11);
+    }
+
+    #[test]
+    fn expect_magic() {
+        let mut cur = Cursor::new(b"fLaCRIFF");
+        cur.expect_magic(b"fLaC").unwrap();
+        assert_eq!(cur.position(), 4);
+
+        let err = cur.expect_magic(b"OggS").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(cur.position(), 8);
+
+        assert_eq!(
+            cur.expect_magic(b"x").unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -6,6 +6,7 @@ use std::cmp::{max, min};
 use std::collections::HashMap;
 use std::fmt;
 use std::marker::PhantomData;
+use std::mem;
 
 use num_traits::AsPrimitive;
 
@@ -90,6 +91,14 @@ pub struct Codebook<S> {
 pub trait CodebookReader<S> {
     /// Reads the codeword from a bitstream and returns its value.
     fn read_cb(&mut self, cb: &Codebook<S>) -> Result<S, CodebookError>;
+    /// Peeks the next codeword from a bitstream and returns its value
+    /// and its length in bits without consuming it.
+    fn peek_cb(&self, cb: &Codebook<S>) -> Result<(S, usize), CodebookError>;
+    /// Tells whether the next bits form a valid codeword, without
+    /// consuming them.
+    fn can_read_cb(&self, cb: &Codebook<S>) -> bool {
+        self.peek_cb(cb).is_ok()
+    }
 }
 
 /// Returns the reversed sequence of bits passed as input.
@@ -239,13 +248,14 @@ fn build_esc_lut(
     table: &mut Vec<u32>,
     mode: CodebookMode,
     bucket: &CodeBucket,
+    max_lut_bits: u8,
 ) -> Result<(), CodebookError> {
     let mut escape_list: EscapeCodes = HashMap::new();
-    let maxlen = min(bucket.maxlen, MAX_LUT_BITS);
+    let maxlen = min(bucket.maxlen, max_lut_bits);
 
     for code in &bucket.codes {
         let bits = code.bits;
-        if code.bits <= MAX_LUT_BITS {
+        if code.bits <= max_lut_bits {
             fill_lut(
                 table,
                 mode,
@@ -257,16 +267,16 @@ fn build_esc_lut(
                 false,
             );
         } else {
-            let ckey = extract_lut_part(code.code, bits, MAX_LUT_BITS, mode);
-            let cval = extract_esc_part(code.code, bits, MAX_LUT_BITS, mode);
-            add_esc_code(&mut escape_list, ckey, cval, bits - MAX_LUT_BITS, code.idx);
+            let ckey = extract_lut_part(code.code, bits, max_lut_bits, mode);
+            let cval = extract_esc_part(code.code, bits, max_lut_bits, mode);
+            add_esc_code(&mut escape_list, ckey, cval, bits - max_lut_bits, code.idx);
         }
     }
 
     let cur_offset = bucket.offset;
     for (ckey, sec_bucket) in &mut escape_list {
         let key = *ckey;
-        let maxlen = min(sec_bucket.maxlen, MAX_LUT_BITS);
+        let maxlen = min(sec_bucket.maxlen, max_lut_bits);
         let new_off = resize_table(table, maxlen);
         fill_lut(
             table,
@@ -274,7 +284,7 @@ fn build_esc_lut(
             cur_offset,
             key,
             maxlen,
-            MAX_LUT_BITS,
+            max_lut_bits,
             new_off,
             true,
         );
@@ -282,7 +292,7 @@ fn build_esc_lut(
     }
 
     for sec_bucket in escape_list.values() {
-        build_esc_lut(table, mode, sec_bucket)?;
+        build_esc_lut(table, mode, sec_bucket, max_lut_bits)?;
     }
 
     Ok(())
@@ -292,6 +302,58 @@ impl<S: Copy> Codebook<S> {
     /// Constructs a new `Codebook` instance using provided
     /// codebook description and mode.
     pub fn new(cb: &dyn CodebookDescReader<S>, mode: CodebookMode) -> Result<Self, CodebookError> {
+        Self::with_lut_bits(cb, mode, MAX_LUT_BITS)
+    }
+
+    /// Constructs a new `Codebook` instance choosing the lookup table width
+    /// from the codeword length distribution, up to `max_lut_bits`.
+    ///
+    /// The width is the smallest one fitting the codewords that account
+    /// for most of the probability implied by their lengths, so skewed
+    /// codebooks get a smaller table while their rare long codewords
+    /// are resolved through escapes.
+    pub fn new_auto(
+        cb: &dyn CodebookDescReader<S>,
+        mode: CodebookMode,
+        max_lut_bits: u8,
+    ) -> Result<Self, CodebookError> {
+        // Probabilities scaled by 2^32, a codeword of length n has 2^-n
+        const ONE: u64 = 1 << 32;
+        const COVERED: u64 = ONE - (ONE >> 5);
+
+        if max_lut_bits == 0 || max_lut_bits > 32 {
+            return Err(InvalidCodebook);
+        }
+
+        let mut mass = [0u64; 33];
+        for i in 0..cb.len() {
+            match cb.bits(i) {
+                0 => {}
+                bits if bits > 32 => return Err(InvalidCodebook),
+                bits => mass[bits as usize] += ONE >> bits,
+            }
+        }
+        let total: u64 = mass.iter().sum();
+        let maxbits = mass.iter().rposition(|&m| m > 0).unwrap_or(0) as u8;
+
+        let mut covered = 0;
+        let mut lut_bits = min(maxbits, max_lut_bits);
+        for (bits, &m) in mass.iter().enumerate().take(lut_bits as usize + 1) {
+            covered += m;
+            if (covered as u128) * (ONE as u128) >= (total as u128) * (COVERED as u128) {
+                lut_bits = bits as u8;
+                break;
+            }
+        }
+
+        Self::with_lut_bits(cb, mode, lut_bits.max(1))
+    }
+
+    fn with_lut_bits(
+        cb: &dyn CodebookDescReader<S>,
+        mode: CodebookMode,
+        max_lut_bits: u8,
+    ) -> Result<Self, CodebookError> {
         let mut maxbits = 0;
         let mut nnz = 0;
         let mut escape_list: EscapeCodes = HashMap::new();
@@ -303,11 +365,11 @@ impl<S: Copy> Codebook<S> {
                 nnz += 1;
             }
             maxbits = max(bits, maxbits);
-            if bits > MAX_LUT_BITS {
+            if bits > max_lut_bits {
                 let code = cb.code(i);
-                let ckey = extract_lut_part(code, bits, MAX_LUT_BITS, mode);
-                let cval = extract_esc_part(code, bits, MAX_LUT_BITS, mode);
-                add_esc_code(&mut escape_list, ckey, cval, bits - MAX_LUT_BITS, symidx);
+                let ckey = extract_lut_part(code, bits, max_lut_bits, mode);
+                let cval = extract_esc_part(code, bits, max_lut_bits, mode);
+                add_esc_code(&mut escape_list, ckey, cval, bits - max_lut_bits, symidx);
             }
             if bits > 0 {
                 symidx += 1;
@@ -317,8 +379,8 @@ impl<S: Copy> Codebook<S> {
             return Err(InvalidCodebook);
         }
 
-        if maxbits > MAX_LUT_BITS {
-            maxbits = MAX_LUT_BITS;
+        if maxbits > max_lut_bits {
+            maxbits = max_lut_bits;
         }
 
         let tab_len = 1 << maxbits;
@@ -333,14 +395,14 @@ impl<S: Copy> Codebook<S> {
             if bits == 0 {
                 continue;
             }
-            if bits <= MAX_LUT_BITS {
+            if bits <= max_lut_bits {
                 fill_lut(&mut table, mode, 0, code, bits, maxbits, symidx, false);
             } else {
-                let ckey = extract_lut_part(code, bits, MAX_LUT_BITS, mode) as usize;
+                let ckey = extract_lut_part(code, bits, max_lut_bits, mode) as usize;
                 if table[ckey] == TABLE_FILL_VALUE {
                     let key = ckey as u32;
                     if let Some(bucket) = escape_list.get_mut(&key) {
-                        let maxlen = min(bucket.maxlen, MAX_LUT_BITS);
+                        let maxlen = min(bucket.maxlen, max_lut_bits);
                         let new_off = resize_table(&mut table, maxlen);
                         fill_lut(
                             &mut table,
@@ -348,7 +410,7 @@ impl<S: Copy> Codebook<S> {
                             0,
                             key,
                             maxlen,
-                            MAX_LUT_BITS,
+                            max_lut_bits,
                             new_off,
                             true,
                         );
@@ -360,7 +422,7 @@ impl<S: Copy> Codebook<S> {
         }
 
         for bucket in escape_list.values() {
-            build_esc_lut(&mut table, mode, bucket)?;
+            build_esc_lut(&mut table, mode, bucket, max_lut_bits)?;
         }
 
         for i in 0..cb.len() {
@@ -375,6 +437,54 @@ impl<S: Copy> Codebook<S> {
             lut_bits: maxbits,
         })
     }
+
+    /// Returns the width in bits of the first level lookup table.
+    pub fn lut_bits(&self) -> u8 {
+        self.lut_bits
+    }
+
+    /// Returns the codebook symbols in codeword index order.
+    pub fn symbols(&self) -> &[S] {
+        &self.syms
+    }
+
+    /// Returns the number of symbols in the codebook.
+    pub fn len(&self) -> usize {
+        self.syms.len()
+    }
+
+    /// Tells if the codebook has no symbols.
+    pub fn is_empty(&self) -> bool {
+        self.syms.is_empty()
+    }
+
+    /// Returns the memory used by the lookup table and the symbols, in bytes.
+    pub fn memory_size(&self) -> usize {
+        self.table.len() * mem::size_of::<u32>() + self.syms.len() * mem::size_of::<S>()
+    }
+
+    /// Checks whether a set of codeword lengths forms a complete prefix code
+    /// through the Kraft-McMillan inequality.
+    ///
+    /// Returns `Ok(false)` if some bit sequences are not assigned to
+    /// any codeword and `InvalidCodebook` if the set is oversubscribed.
+    /// Zero lengths denote unused symbols.
+    pub fn check_complete(lengths: &[u8]) -> Result<bool, CodebookError> {
+        const ONE: u64 = 1 << 32;
+        let mut sum = 0;
+
+        for &len in lengths.iter().filter(|&&len| len > 0) {
+            if len > 32 {
+                return Err(InvalidCodebook);
+            }
+            sum += ONE >> len;
+            if sum > ONE {
+                return Err(InvalidCodebook);
+            }
+        }
+
+        Ok(sum == ONE)
+    }
 }
 
 impl<'a, S: Copy, B: BitRead<'a>> CodebookReader<S> for B {
@@ -403,6 +513,14 @@ impl<'a, S: Copy, B: BitRead<'a>> CodebookReader<S> for B {
         }
         Ok(cb.syms[idx])
     }
+
+    fn peek_cb(&self, cb: &Codebook<S>) -> Result<(S, usize), CodebookError> {
+        let mut tmp = *self;
+        let start = tmp.consumed();
+        let sym = tmp.read_cb(cb)?;
+
+        Ok((sym, tmp.consumed() - start))
+    }
 }
 
 impl<S: Copy> CodebookDescReader<S> for Vec<FullCodebookDesc<S>> {
@@ -441,7 +559,7 @@ impl CodebookDescReader<u32> for Vec<ShortCodebookDesc> {
     }
 }
 
-impl<'a, S: Copy> CodebookDescReader<S> for &'a [FullCodebookDesc<S>] {
+impl<S: Copy> CodebookDescReader<S> for &[FullCodebookDesc<S>] {
     fn bits(&self, idx: usize) -> u8 {
         self[idx].bits
     }
@@ -459,7 +577,7 @@ impl<'a, S: Copy> CodebookDescReader<S> for &'a [FullCodebookDesc<S>] {
     }
 }
 
-impl<'a> CodebookDescReader<u32> for &'a [ShortCodebookDesc] {
+impl CodebookDescReader<u32> for &[ShortCodebookDesc] {
     fn bits(&self, idx: usize) -> u8 {
         self[idx].bits
     }
@@ -508,12 +626,125 @@ where
     }
 }
 
+// Package-merge item: either a symbol or a pair of items.
+enum HuffNode {
+    Leaf(usize),
+    Package(usize, usize),
+}
+
+/// Computes length-limited Huffman codeword lengths from symbol frequencies.
+///
+/// Symbols with a zero frequency get a zero length, the others get
+/// lengths of at most `max_len` bits forming a complete prefix code
+/// (a single used symbol gets a 1-bit code).
+///
+/// Returns `InvalidCodebook` if `max_len` exceeds 32 or is too small
+/// to code all the used symbols.
+pub fn build_huffman(freqs: &[u64], max_len: u8) -> Result<Vec<u8>, CodebookError> {
+    let mut lengths = vec![0u8; freqs.len()];
+    let mut used: Vec<usize> = (0..freqs.len()).filter(|&i| freqs[i] > 0).collect();
+
+    if max_len > 32 {
+        return Err(InvalidCodebook);
+    }
+    match used.len() {
+        0 => return Ok(lengths),
+        _ if max_len == 0 => return Err(InvalidCodebook),
+        1 => {
+            lengths[used[0]] = 1;
+            return Ok(lengths);
+        }
+        n if n as u64 > 1 << max_len => return Err(InvalidCodebook),
+        _ => {}
+    }
+
+    used.sort_by_key(|&i| freqs[i]);
+
+    let mut nodes: Vec<HuffNode> = used.iter().map(|&i| HuffNode::Leaf(i)).collect();
+    let leaves: Vec<(u64, usize)> = used
+        .iter()
+        .enumerate()
+        .map(|(n, &i)| (freqs[i], n))
+        .collect();
+    let mut list = leaves.clone();
+
+    for _ in 1..max_len {
+        let packages: Vec<(u64, usize)> = list
+            .chunks_exact(2)
+            .map(|pair| {
+                nodes.push(HuffNode::Package(pair[0].1, pair[1].1));
+                (pair[0].0.saturating_add(pair[1].0), nodes.len() - 1)
+            })
+            .collect();
+
+        list = Vec::with_capacity(leaves.len() + packages.len());
+        let (mut l, mut p) = (leaves.iter().peekable(), packages.iter().peekable());
+        while let (Some(&&leaf), Some(&&package)) = (l.peek(), p.peek()) {
+            if leaf.0 <= package.0 {
+                list.push(leaf);
+                l.next();
+            } else {
+                list.push(package);
+                p.next();
+            }
+        }
+        list.extend(l);
+        list.extend(p);
+    }
+
+    let mut stack: Vec<usize> = list[..2 * used.len() - 2].iter().map(|it| it.1).collect();
+    while let Some(idx) = stack.pop() {
+        match nodes[idx] {
+            HuffNode::Leaf(i) => lengths[i] += 1,
+            HuffNode::Package(a, b) => stack.extend([a, b]),
+        }
+    }
+
+    Ok(lengths)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     const BITS: [u8; 8] = [0b01011011, 0b10111100, 0b11111111, 0, 0, 0, 0, 0];
 
+    #[test]
+    fn check_complete() {
+        let check = Codebook::<u32>::check_complete;
+
+        assert!(check(&[1, 2, 3, 3]).unwrap());
+        assert!(check(&[1, 0, 2, 0, 3, 3]).unwrap());
+        assert!(check(&[32; 2]).is_ok_and(|c| !c));
+        assert!(!check(&[1, 2, 3]).unwrap());
+        assert!(!check(&[]).unwrap());
+        assert!(check(&[1, 1, 2]).is_err());
+        assert!(check(&[33]).is_err());
+    }
+
+    #[test]
+    fn build_huffman() {
+        let check = Codebook::<u32>::check_complete;
+
+        let lengths = super::build_huffman(&[1, 1, 2, 4, 8, 16], 8).unwrap();
+        assert_eq!(lengths, [5, 5, 4, 3, 2, 1]);
+
+        let freqs = [1, 0, 1, 2, 4, 0, 8, 16];
+        let lengths = super::build_huffman(&freqs, 3).unwrap();
+        assert!(check(&lengths).unwrap());
+        assert!(lengths.iter().all(|&l| l <= 3));
+        for (&f, &l) in freqs.iter().zip(&lengths) {
+            assert_eq!(f == 0, l == 0);
+        }
+
+        assert_eq!(super::build_huffman(&[0, 5, 0], 4).unwrap(), [0, 1, 0]);
+        assert_eq!(super::build_huffman(&[0, 0], 4).unwrap(), [0, 0]);
+
+        assert_matches::assert_matches!(super::build_huffman(&[1, 2, 3], 1), Err(InvalidCodebook));
+        assert_matches::assert_matches!(super::build_huffman(&[5], 0), Err(InvalidCodebook));
+        assert_matches::assert_matches!(super::build_huffman(&[1, 2], 33), Err(InvalidCodebook));
+    }
+
     #[test]
     fn test_refill_codebook_msb() {
         // make sure reading codes across 8-byte boundary works
@@ -594,6 +825,9 @@ mod test {
         let mut br = BitReadBE::new(buf);
         let cb = Codebook::new(&cb_desc, CodebookMode::MSB).unwrap();
 
+        assert_eq!(cb.len(), 4);
+        assert_eq!(cb.symbols(), &[16, -3, 42, -42]);
+
         assert_eq!(br.read_cb(&cb).unwrap(), 16);
         assert_eq!(br.read_cb(&cb).unwrap(), -3);
         assert_eq!(br.read_cb(&cb).unwrap(), 42);
@@ -606,6 +840,120 @@ mod test {
         }
     }
 
+    #[test]
+    fn peek_cb() {
+        let cb_desc: Vec<FullCodebookDesc<i8>> = vec![
+            FullCodebookDesc {
+                code: 0b0,
+                bits: 1,
+                sym: 16,
+            },
+            FullCodebookDesc {
+                code: 0b10,
+                bits: 2,
+                sym: -3,
+            },
+        ];
+        let buf = &BITS;
+        let mut br = BitReadBE::new(buf);
+        let cb = Codebook::new(&cb_desc, CodebookMode::MSB).unwrap();
+
+        assert_eq!(br.peek_cb(&cb).unwrap(), (16, 1));
+        assert_eq!(br.peek_cb(&cb).unwrap(), (16, 1));
+        assert_eq!(br.consumed(), 0);
+        assert_eq!(br.read_cb(&cb).unwrap(), 16);
+        assert_eq!(br.peek_cb(&cb).unwrap(), (-3, 2));
+        assert_eq!(br.consumed(), 1);
+    }
+
+    #[test]
+    fn memory_size() {
+        let cb_desc: Vec<FullCodebookDesc<i8>> = (1..=4)
+            .map(|bits| FullCodebookDesc {
+                code: 1,
+                bits,
+                sym: bits as i8,
+            })
+            .collect();
+        let cb = Codebook::new(&cb_desc, CodebookMode::MSB).unwrap();
+        // 4-bit lookup table and 4 one-byte symbols
+        assert_eq!(cb.memory_size(), 16 * 4 + 4);
+
+        let cb_desc: Vec<ShortCodebookDesc> = (1..=16)
+            .map(|bits| ShortCodebookDesc { code: 1, bits })
+            .collect();
+        let cb = Codebook::new(&cb_desc, CodebookMode::MSB).unwrap();
+        assert!(cb.table.len() > 1 << MAX_LUT_BITS);
+        assert_eq!(cb.memory_size(), cb.table.len() * 4 + 16 * 4);
+    }
+
+    #[test]
+    fn can_read_cb() {
+        let cb_desc: Vec<ShortCodebookDesc> = vec![
+            ShortCodebookDesc { code: 0b1, bits: 1 },
+            ShortCodebookDesc {
+                code: 0b01,
+                bits: 2,
+            },
+        ];
+        let cb = Codebook::new(&cb_desc, CodebookMode::MSB).unwrap();
+        let buf = [0b1010_0000u8, 0, 0, 0, 0, 0, 0, 0b0000_0001];
+        let mut br = BitReadBE::new(&buf);
+
+        assert!(br.can_read_cb(&cb));
+        assert_eq!(br.consumed(), 0);
+        assert_eq!(br.read_cb(&cb).unwrap(), 0);
+        assert!(br.can_read_cb(&cb));
+        assert_eq!(br.read_cb(&cb).unwrap(), 1);
+
+        // Only zeroes are left, an invalid prefix
+        assert!(!br.can_read_cb(&cb));
+
+        // A single-bit codeword ends the buffer
+        br.skip_bits(br.available() - 1);
+        assert!(br.can_read_cb(&cb));
+        assert_eq!(br.read_cb(&cb).unwrap(), 0);
+        assert_eq!(br.available(), 0);
+        assert!(!br.can_read_cb(&cb));
+    }
+
+    #[test]
+    fn new_auto() {
+        use crate::bitwrite::{BitWrite, BitWriterStream};
+
+        // Unary-like code: 1, 01, 001, ... plus an all-zero 11-bit codeword
+        let mut cb_desc: Vec<ShortCodebookDesc> = (1..=11)
+            .map(|bits| ShortCodebookDesc { code: 1, bits })
+            .collect();
+        cb_desc.push(ShortCodebookDesc { code: 0, bits: 11 });
+
+        let cb = Codebook::new(&cb_desc, CodebookMode::MSB).unwrap();
+        let auto = Codebook::new_auto(&cb_desc, CodebookMode::MSB, 10).unwrap();
+        assert_eq!(cb.lut_bits(), 10);
+        assert_eq!(auto.lut_bits(), 5);
+        assert!(auto.memory_size() < cb.memory_size());
+
+        let syms = [0, 11, 3, 4, 5, 10, 6, 1, 9, 2, 8, 0, 7, 11];
+        let mut bw = BitWriterStream::new(Vec::new());
+        for &sym in syms.iter() {
+            let desc = &cb_desc[sym];
+            bw.put_bits_32(desc.code, desc.bits as usize).unwrap();
+        }
+        bw.flush().unwrap();
+        let mut buf = bw.into_inner();
+        buf.resize(buf.len() + 8, 0);
+
+        let mut br = BitReadBE::new(&buf);
+        let mut br_auto = BitReadBE::new(&buf);
+        for &sym in syms.iter() {
+            assert_eq!(br.read_cb(&cb).unwrap(), sym as u32);
+            assert_eq!(br_auto.read_cb(&auto).unwrap(), sym as u32);
+        }
+        assert_eq!(br.consumed(), br_auto.consumed());
+
+        assert!(Codebook::new_auto(&cb_desc, CodebookMode::MSB, 0).is_err());
+    }
+
     #[test]
     fn test_short_codebook_msb() {
         let scb_desc: Vec<ShortCodebookDesc> = vec![
@@ -650,6 +998,7 @@ mod test {
         let buf = &BITS;
         let mut br2 = BitReadBE::new(buf);
         let cb = Codebook::new(&scb_desc, CodebookMode::MSB).unwrap();
+        assert_eq!(cb.symbols(), &[0, 2, 5, 7, 8, 9, 10, 11, 12]);
         assert_eq!(br2.read_cb(&cb).unwrap(), 0);
         assert_eq!(br2.read_cb(&cb).unwrap(), 2);
         assert_eq!(br2.read_cb(&cb).unwrap(), 5);
@@ -3,6 +3,8 @@
 #![deny(missing_docs, clippy::undocumented_unsafe_blocks)]
 
 pub mod bitread;
+pub mod bitwrite;
 pub mod byteread;
 pub mod bytewrite;
 pub mod codebook;
+pub mod crc;
@@ -1,3 +1,22 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// Capabilities advertised by a codec.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct CodecCapabilities: u32 {
+        /// The codec buffers data before producing output.
+        const DELAY = 1 << 0;
+        /// The codec is backed by hardware.
+        const HARDWARE = 1 << 1;
+        /// The codec is lossless.
+        const LOSSLESS = 1 << 2;
+        /// The codec is experimental and may misbehave.
+        const EXPERIMENTAL = 1 << 3;
+        /// The codec can use multiple threads.
+        const THREADS = 1 << 4;
+    }
+}
+
 /// Defines a series of methods to interact with a list of codec descriptors.
 pub trait CodecList: Sized {
     /// The type of the structure used to describe a codec.
@@ -1,14 +1,15 @@
 use std::collections::HashMap;
 
-use av_data::frame::ArcFrame;
+use av_data::frame::{ArcFrame, FramePool};
 use av_data::packet::Packet;
+use av_data::params::CodecParams;
+use av_data::value::Value;
 
-use crate::common::CodecList;
+use crate::common::{CodecCapabilities, CodecList};
 use crate::error::*;
 
 /// Used to interact with a decoder.
 pub trait Decoder: Send + Sync {
-    // TODO support codec configuration using set_option
     // fn open(&mut self) -> Result<()>;
     /// Saves the extra data contained in a codec.
     fn set_extradata(&mut self, extra: &[u8]);
@@ -20,6 +21,18 @@ pub trait Decoder: Send + Sync {
     fn configure(&mut self) -> Result<()>;
     /// Tells decoder to clear its internal state.
     fn flush(&mut self) -> Result<()>;
+    /// Tells whether the decoder has frames ready to be received.
+    fn pending(&self) -> bool {
+        false
+    }
+    /// Sets a decoder option.
+    fn set_option(&mut self, key: &str, _val: Value) -> Result<()> {
+        Err(Error::Unsupported(format!("{} key", key)))
+    }
+    /// Returns the parameters of the decoded output, if already known.
+    fn output_params(&self) -> Option<CodecParams> {
+        None
+    }
 }
 
 /// Codec descriptor.
@@ -35,13 +48,28 @@ pub struct Descr {
     pub desc: &'static str,
     /// The codec MIME.
     pub mime: &'static str,
-    // TODO more fields regarding capabilities
+    /// The codec capabilities.
+    pub capabilities: CodecCapabilities,
+}
+
+/// State of a decoder reported by `Context::poll`.
+#[derive(Debug)]
+pub enum DecodeState {
+    /// A decoded frame.
+    Frame(ArcFrame),
+    /// The decoder needs more packets to produce a frame.
+    NeedMorePackets,
+    /// The end of the input was signaled and all the frames were returned.
+    Eof,
 }
 
 /// Auxiliary structure to encapsulate a decoder object and
 /// its additional data.
 pub struct Context<D: Decoder> {
     dec: D,
+    capabilities: CodecCapabilities,
+    eof: bool,
+    pool: Option<FramePool>,
     // TODO: Queue up packets/frames
 }
 
@@ -55,15 +83,47 @@ impl<D: Decoder> Context<D> {
     ) -> Option<Self> {
         codecs.by_name(name).map(|builder| Context {
             dec: builder.create(),
+            capabilities: builder.describe().capabilities,
+            eof: false,
+            pool: None,
         })
     }
+
+    /// Retrieves a codec descriptor from a codec list through the codec id
+    /// of the given parameters, creates the relative decoder, and
+    /// encapsulates it into a new `Context`.
+    ///
+    /// The extradata of the parameters, if any, is passed to the decoder.
+    pub fn from_params<T: Descriptor<OutputDecoder = D> + ?Sized>(
+        codecs: &Codecs<T>,
+        params: &CodecParams,
+    ) -> Option<Self> {
+        let mut ctx = Self::by_name(codecs, params.codec_id.as_deref()?)?;
+
+        if let Some(ref extradata) = params.extradata {
+            ctx.set_extradata(extradata);
+        }
+
+        Some(ctx)
+    }
+
+    /// Returns the capabilities of the codec.
+    pub fn capabilities(&self) -> CodecCapabilities {
+        self.capabilities
+    }
     /// Saves the extra data contained in a codec.
     pub fn set_extradata(&mut self, extra: &[u8]) {
         self.dec.set_extradata(extra);
     }
 
     /// Sends to the decoder a packet to be decoded.
+    ///
+    /// An end-of-stream packet is forwarded to the decoder so that it
+    /// starts draining, and is equivalent to calling `send_eof`.
     pub fn send_packet(&mut self, pkt: &Packet) -> Result<()> {
+        if pkt.is_eos {
+            self.eof = true;
+        }
         self.dec.send_packet(pkt)
     }
     /// Returns a decoded frame.
@@ -77,13 +137,73 @@ impl<D: Decoder> Context<D> {
 
     /// Tells decoder to clear its internal state.
     pub fn flush(&mut self) -> Result<()> {
+        self.eof = false;
         self.dec.flush()
     }
 
+    /// Signals that no more packets will be sent to the decoder,
+    /// sending it an end-of-stream packet.
+    pub fn send_eof(&mut self) -> Result<()> {
+        self.send_packet(&Packet::eos(-1))
+    }
+
+    /// Tries to get a decoded frame, telling whether more packets are needed
+    /// or the decoder is drained once `send_eof` was called.
+    pub fn poll(&mut self) -> Result<DecodeState> {
+        match self.dec.receive_frame() {
+            Ok(frame) => Ok(DecodeState::Frame(frame)),
+            Err(Error::MoreDataNeeded) if self.eof => Ok(DecodeState::Eof),
+            Err(Error::MoreDataNeeded) => Ok(DecodeState::NeedMorePackets),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sets a decoder option.
+    pub fn set_option<'a, V>(&mut self, key: &str, val: V) -> Result<()>
+    where
+        V: Into<Value<'a>>,
+    {
+        self.dec.set_option(key, val.into())
+    }
+
+    /// Sets the number of threads the codec may use.
+    ///
+    /// It is a shorthand for the `threads` option.
+    pub fn set_threads(&mut self, n: usize) -> Result<()> {
+        self.set_option("threads", n as u64)
+    }
+
+    /// Tells whether `receive_frame` should be called again.
+    pub fn has_pending(&self) -> bool {
+        self.dec.pending()
+    }
+
     /// Returns the underlying decoder.
     pub fn decoder(&self) -> &D {
         &self.dec
     }
+
+    /// Preallocates `count` output frames from the output parameters
+    /// reported by the decoder.
+    ///
+    /// Fails if the decoder does not know its output parameters yet, or if
+    /// they do not describe a frame completely.
+    pub fn prealloc_frames(&mut self, count: usize) -> Result<()> {
+        let params = self
+            .dec
+            .output_params()
+            .ok_or(Error::ConfigurationIncomplete)?;
+        let pool = FramePool::from_params(&params, count).ok_or(Error::ConfigurationIncomplete)?;
+
+        self.pool = Some(pool);
+
+        Ok(())
+    }
+
+    /// Returns the preallocated frame pool, if any.
+    pub fn frame_pool(&mut self) -> Option<&mut FramePool> {
+        self.pool.as_mut()
+    }
 }
 
 /// Used to get the descriptor of a codec and create its own decoder.
@@ -129,9 +249,15 @@ mod test {
 
     mod dummy {
         use super::super::*;
+        use av_data::frame::{Frame, FrameType, VideoInfo};
+        use av_data::params::{self, MediaKind};
+        use av_data::pixel::formats::YUV420;
+        use std::sync::Arc;
 
         pub struct Dec {
-            state: usize,
+            pub state: usize,
+            queued: usize,
+            pub threads: usize,
         }
 
         pub struct Des {
@@ -142,7 +268,11 @@ mod test {
             type OutputDecoder = Dec;
 
             fn create(&self) -> Self::OutputDecoder {
-                Dec { state: 0 }
+                Dec {
+                    state: 0,
+                    queued: 0,
+                    threads: 1,
+                }
             }
 
             fn describe(&self) -> &Descr {
@@ -161,16 +291,56 @@ mod test {
                     self.state = 12;
                 }
             }
-            fn send_packet(&mut self, _packet: &Packet) -> Result<()> {
+            fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+                if packet.is_eos {
+                    return Ok(());
+                }
                 self.state += 1;
+                self.queued += 2;
                 Ok(())
             }
             fn receive_frame(&mut self) -> Result<ArcFrame> {
-                unimplemented!()
+                if self.queued == 0 {
+                    return Err(Error::MoreDataNeeded);
+                }
+                self.queued -= 1;
+
+                let info = VideoInfo::new(16, 16, false, FrameType::I, Arc::new(*YUV420));
+                Ok(Arc::new(Frame::new_default_frame(info, None)))
             }
             fn flush(&mut self) -> Result<()> {
                 Ok(())
             }
+            fn pending(&self) -> bool {
+                self.queued > 0
+            }
+            fn output_params(&self) -> Option<CodecParams> {
+                if self.state == 0 {
+                    return None;
+                }
+
+                Some(CodecParams {
+                    kind: Some(MediaKind::Video(params::VideoInfo {
+                        width: 16,
+                        height: 16,
+                        format: Some(Arc::new(*YUV420)),
+                        frame_rate: None,
+                    })),
+                    codec_id: Some("dummy".to_owned()),
+                    extradata: None,
+                    bit_rate: 0,
+                    convergence_window: 0,
+                    delay: 0,
+                })
+            }
+            fn set_option(&mut self, key: &str, val: Value) -> Result<()> {
+                match (key, val) {
+                    ("threads", Value::U64(v)) => self.threads = v as usize,
+                    _ => return Err(Error::Unsupported(format!("{} key", key))),
+                }
+
+                Ok(())
+            }
         }
 
         pub const DUMMY_DESCR: &Des = &Des {
@@ -179,15 +349,208 @@ mod test {
                 name: "dummy",
                 desc: "Dummy decoder",
                 mime: "x-application/dummy",
+                capabilities: CodecCapabilities::THREADS,
             },
         };
     }
     use self::dummy::DUMMY_DESCR;
 
+    // Holds back the last decoded frame until the end of the stream.
+    mod delay {
+        use super::super::*;
+        use av_data::frame::{Frame, FrameType, VideoInfo};
+        use av_data::pixel::formats::YUV420;
+        use std::sync::Arc;
+
+        pub struct Dec {
+            held: usize,
+            draining: bool,
+        }
+
+        pub struct Des {
+            descr: Descr,
+        }
+
+        impl Descriptor for Des {
+            type OutputDecoder = Dec;
+
+            fn create(&self) -> Self::OutputDecoder {
+                Dec {
+                    held: 0,
+                    draining: false,
+                }
+            }
+
+            fn describe(&self) -> &Descr {
+                &self.descr
+            }
+        }
+
+        impl Decoder for Dec {
+            fn configure(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn set_extradata(&mut self, _extra: &[u8]) {}
+            fn send_packet(&mut self, packet: &Packet) -> Result<()> {
+                if packet.is_eos {
+                    self.draining = true;
+                } else {
+                    self.held += 1;
+                }
+                Ok(())
+            }
+            fn receive_frame(&mut self) -> Result<ArcFrame> {
+                if self.held == 0 || (self.held == 1 && !self.draining) {
+                    return Err(Error::MoreDataNeeded);
+                }
+                self.held -= 1;
+
+                let info = VideoInfo::new(16, 16, false, FrameType::I, Arc::new(*YUV420));
+                Ok(Arc::new(Frame::new_default_frame(info, None)))
+            }
+            fn flush(&mut self) -> Result<()> {
+                self.held = 0;
+                self.draining = false;
+                Ok(())
+            }
+        }
+
+        pub const DELAY_DESCR: &Des = &Des {
+            descr: Descr {
+                codec: "delay",
+                name: "delay",
+                desc: "Delaying decoder",
+                mime: "x-application/delay",
+                capabilities: CodecCapabilities::empty(),
+            },
+        };
+    }
+    use self::delay::DELAY_DESCR;
+
     #[test]
     fn lookup() {
         let codecs = Codecs::from_list(&[DUMMY_DESCR]);
 
         let _dec = codecs.by_name("dummy").unwrap();
     }
+
+    #[test]
+    fn from_params() {
+        let codecs = Codecs::from_list(&[DUMMY_DESCR]);
+        let mut params = CodecParams {
+            kind: None,
+            codec_id: Some("dummy".to_owned()),
+            extradata: Some(vec![0; 8]),
+            bit_rate: 0,
+            convergence_window: 0,
+            delay: 0,
+        };
+
+        let ctx = Context::from_params(&codecs, &params).unwrap();
+        assert_eq!(ctx.decoder().state, 42);
+
+        params.codec_id = Some("unknown".to_owned());
+        assert!(Context::from_params(&codecs, &params).is_none());
+        params.codec_id = None;
+        assert!(Context::from_params(&codecs, &params).is_none());
+    }
+
+    #[test]
+    fn poll() {
+        let codecs = Codecs::from_list(&[DUMMY_DESCR]);
+        let mut ctx = Context::by_name(&codecs, "dummy").unwrap();
+
+        assert!(matches!(ctx.poll(), Ok(DecodeState::NeedMorePackets)));
+        ctx.send_packet(&Packet::new()).unwrap();
+        ctx.send_eof().unwrap();
+        assert!(matches!(ctx.poll(), Ok(DecodeState::Frame(_))));
+        assert!(matches!(ctx.poll(), Ok(DecodeState::Frame(_))));
+        assert!(matches!(ctx.poll(), Ok(DecodeState::Eof)));
+
+        ctx.flush().unwrap();
+        assert!(matches!(ctx.poll(), Ok(DecodeState::NeedMorePackets)));
+
+        ctx.send_packet(&Packet::eos(0)).unwrap();
+        assert!(matches!(ctx.poll(), Ok(DecodeState::Eof)));
+    }
+
+    #[test]
+    fn drain_on_eos() {
+        let codecs = Codecs::from_list(&[DELAY_DESCR]);
+        let mut ctx = Context::by_name(&codecs, "delay").unwrap();
+
+        ctx.send_packet(&Packet::new()).unwrap();
+        ctx.send_packet(&Packet::new()).unwrap();
+        assert!(matches!(ctx.poll(), Ok(DecodeState::Frame(_))));
+        assert!(matches!(ctx.poll(), Ok(DecodeState::NeedMorePackets)));
+
+        ctx.send_packet(&Packet::eos(0)).unwrap();
+        assert!(matches!(ctx.poll(), Ok(DecodeState::Frame(_))));
+        assert!(matches!(ctx.poll(), Ok(DecodeState::Eof)));
+
+        ctx.flush().unwrap();
+        ctx.send_packet(&Packet::new()).unwrap();
+        ctx.send_eof().unwrap();
+        assert!(matches!(ctx.poll(), Ok(DecodeState::Frame(_))));
+        assert!(matches!(ctx.poll(), Ok(DecodeState::Eof)));
+    }
+
+    #[test]
+    fn set_threads() {
+        let codecs = Codecs::from_list(&[DUMMY_DESCR]);
+        let mut ctx = Context::by_name(&codecs, "dummy").unwrap();
+
+        ctx.set_threads(4).unwrap();
+        assert_eq!(ctx.decoder().threads, 4);
+        assert!(ctx.set_option("unknown", 1u64).is_err());
+    }
+
+    #[test]
+    fn prealloc_frames() {
+        let codecs = Codecs::from_list(&[DUMMY_DESCR]);
+        let mut ctx = Context::by_name(&codecs, "dummy").unwrap();
+
+        assert!(ctx.prealloc_frames(4).is_err());
+        assert!(ctx.frame_pool().is_none());
+
+        ctx.set_extradata(&[]);
+        ctx.prealloc_frames(4).unwrap();
+
+        let pool = ctx.frame_pool().unwrap();
+        assert_eq!(pool.len(), 4);
+        let info = pool.get_kind().get_video_info().unwrap();
+        assert_eq!((info.width, info.height), (16, 16));
+
+        let frame = pool.get_frame();
+        assert_eq!(pool.len(), 3);
+        pool.put_frame(frame);
+        assert_eq!(pool.len(), 4);
+    }
+
+    #[test]
+    fn capabilities() {
+        let codecs = Codecs::from_list(&[DUMMY_DESCR]);
+        let ctx = Context::by_name(&codecs, "dummy").unwrap();
+
+        assert!(ctx.capabilities().contains(CodecCapabilities::THREADS));
+        assert!(!ctx.capabilities().contains(CodecCapabilities::EXPERIMENTAL));
+    }
+
+    #[test]
+    fn pending() {
+        let codecs = Codecs::from_list(&[DUMMY_DESCR]);
+        let mut ctx = Context::by_name(&codecs, "dummy").unwrap();
+
+        assert!(!ctx.has_pending());
+        ctx.send_packet(&Packet::new()).unwrap();
+        assert!(ctx.has_pending());
+
+        let mut frames = 0;
+        while ctx.has_pending() {
+            ctx.receive_frame().unwrap();
+            frames += 1;
+        }
+        assert_eq!(frames, 2);
+        assert!(ctx.receive_frame().is_err());
+    }
 }
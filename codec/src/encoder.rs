@@ -1,12 +1,14 @@
 use std::collections::HashMap;
 use std::convert::Into;
 
+use av_data::audiosample::ChannelMap;
 use av_data::frame::ArcFrame;
 use av_data::packet::Packet;
-use av_data::params::CodecParams;
+use av_data::params::{CodecParams, MediaKind};
+use av_data::rational::Rational64;
 use av_data::value::Value;
 
-use crate::common::CodecList;
+use crate::common::{CodecCapabilities, CodecList};
 
 use crate::error::*;
 
@@ -20,6 +22,22 @@ pub trait Encoder: Send {
     fn receive_packet(&mut self) -> Result<Packet>;
     /// Tells encoder to clear its internal state.
     fn flush(&mut self) -> Result<()>;
+    /// Tells whether the encoder has packets ready to be received.
+    fn pending(&self) -> bool {
+        false
+    }
+    /// Returns the sample rates accepted by an audio encoder.
+    ///
+    /// An empty list means that any sample rate is accepted.
+    fn supported_sample_rates(&self) -> Vec<usize> {
+        Vec::new()
+    }
+    /// Returns the channel layouts accepted by an audio encoder.
+    ///
+    /// An empty list means that any channel layout is accepted.
+    fn supported_channel_layouts(&self) -> Vec<ChannelMap> {
+        Vec::new()
+    }
 
     /// Configures the encoder.
     fn configure(&mut self) -> Result<()>;
@@ -37,6 +55,7 @@ pub trait Encoder: Send {
 /// its additional data.
 pub struct Context<E: Encoder> {
     enc: E,
+    capabilities: CodecCapabilities,
     // TODO: Queue up packets/frames
     // TODO: Store here more information
     // TODO: Have a resource pool
@@ -53,9 +72,15 @@ impl<E: Encoder> Context<E> {
     ) -> Option<Self> {
         codecs.by_name(name).map(|builder| Context {
             enc: builder.create(),
+            capabilities: builder.describe().capabilities,
         })
     }
 
+    /// Returns the capabilities of the codec.
+    pub fn capabilities(&self) -> CodecCapabilities {
+        self.capabilities
+    }
+
     /// Configures the encoder.
     pub fn configure(&mut self) -> Result<()> {
         self.enc.configure()
@@ -80,6 +105,13 @@ impl<E: Encoder> Context<E> {
         self.enc.set_option(key, val.into())
     }
 
+    /// Sets the number of threads the codec may use.
+    ///
+    /// It is a shorthand for the `threads` option.
+    pub fn set_threads(&mut self, n: usize) -> Result<()> {
+        self.set_option("threads", n as u64)
+    }
+
     /// Returns the extra data added by an encoder to a codec.
     pub fn get_extradata(&mut self) -> Option<Vec<u8>> {
         self.enc.get_extradata()
@@ -99,10 +131,53 @@ impl<E: Encoder> Context<E> {
         self.enc.flush()
     }
 
+    /// Tells whether `receive_packet` should be called again.
+    pub fn has_pending(&self) -> bool {
+        self.enc.pending()
+    }
+
+    /// Returns the sample rates accepted by the encoder, if restricted.
+    pub fn supported_sample_rates(&self) -> Vec<usize> {
+        self.enc.supported_sample_rates()
+    }
+
+    /// Returns the channel layouts accepted by the encoder, if restricted.
+    pub fn supported_channel_layouts(&self) -> Vec<ChannelMap> {
+        self.enc.supported_channel_layouts()
+    }
+
     /// Returns the underlying encoder.
     pub fn encoder(&self) -> &E {
         &self.enc
     }
+
+    /// Compensates the codec delay on the presentation timestamp
+    /// of an encoded packet.
+    ///
+    /// The encoder `delay` is expressed in samples for audio codecs, and it is
+    /// converted to the packet timebase through the sample rate. For other
+    /// codecs, or if the packet has no timebase, it is subtracted as-is.
+    ///
+    /// The resulting timestamp is clamped at zero.
+    pub fn apply_codec_delay(&self, pkt: &mut Packet) -> Result<()> {
+        let params = self.enc.get_params()?;
+        let delay = params.delay as i64;
+
+        let delay = match (&params.kind, pkt.t.timebase) {
+            (Some(MediaKind::Audio(info)), Some(tb)) if info.rate > 0 => {
+                (Rational64::new(delay, info.rate as i64) / tb)
+                    .round()
+                    .to_integer()
+            }
+            _ => delay,
+        };
+
+        if let Some(pts) = pkt.t.pts {
+            pkt.t.pts = Some((pts - delay).max(0));
+        }
+
+        Ok(())
+    }
 }
 
 /// Codec descriptor.
@@ -118,7 +193,8 @@ pub struct Descr {
     pub desc: &'static str,
     /// The codec MIME.
     pub mime: &'static str,
-    // TODO more fields regarding capabilities
+    /// The codec capabilities.
+    pub capabilities: CodecCapabilities,
 }
 
 /// Used to get the descriptor of a codec and create its own encoder.
@@ -172,6 +248,7 @@ mod test {
             w: Option<usize>,
             h: Option<usize>,
             format: Option<Arc<Formaton>>,
+            pub threads: usize,
         }
 
         pub struct Des {
@@ -187,6 +264,7 @@ mod test {
                     w: None,
                     h: None,
                     format: None,
+                    threads: 1,
                 }
             }
 
@@ -222,6 +300,7 @@ mod test {
                     ("w", Value::U64(v)) => self.w = Some(v as usize),
                     ("h", Value::U64(v)) => self.h = Some(v as usize),
                     ("format", Value::Formaton(f)) => self.format = Some(f),
+                    ("threads", Value::U64(v)) => self.threads = v as usize,
                     _ => return Err(Error::Unsupported(format!("{} key", key))),
                 }
 
@@ -251,6 +330,7 @@ mod test {
                         height: self.w.unwrap(),
                         width: self.h.unwrap(),
                         format: self.format.clone(),
+                        frame_rate: None,
                     })),
                     codec_id: Some("dummy".to_owned()),
                     extradata: self.get_extradata(),
@@ -271,16 +351,127 @@ mod test {
                 name: "dummy",
                 desc: "Dummy encoder",
                 mime: "x-application/dummy",
+                capabilities: CodecCapabilities::THREADS,
             },
         };
+
+        pub struct AudioEnc {
+            pub delay: usize,
+        }
+
+        impl Encoder for AudioEnc {
+            fn configure(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn get_extradata(&self) -> Option<Vec<u8>> {
+                None
+            }
+            fn send_frame(&mut self, _frame: &ArcFrame) -> Result<()> {
+                Ok(())
+            }
+            fn receive_packet(&mut self) -> Result<Packet> {
+                Err(Error::MoreDataNeeded)
+            }
+            fn set_option(&mut self, key: &str, _val: Value) -> Result<()> {
+                Err(Error::Unsupported(format!("{} key", key)))
+            }
+            fn set_params(&mut self, _params: &CodecParams) -> Result<()> {
+                Ok(())
+            }
+            fn get_params(&self) -> Result<CodecParams> {
+                use av_data::params::*;
+
+                Ok(CodecParams {
+                    kind: Some(MediaKind::Audio(AudioInfo {
+                        rate: 48000,
+                        map: None,
+                        format: None,
+                    })),
+                    codec_id: Some("dummy-audio".to_owned()),
+                    extradata: None,
+                    bit_rate: 0,
+                    convergence_window: 0,
+                    delay: self.delay,
+                })
+            }
+            fn flush(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn supported_sample_rates(&self) -> Vec<usize> {
+                vec![48000]
+            }
+            fn supported_channel_layouts(&self) -> Vec<ChannelMap> {
+                vec![ChannelMap::default_map(2)]
+            }
+        }
     }
 
     use self::dummy::DUMMY_DESCR;
 
+    #[test]
+    fn codec_delay() {
+        let ctx = Context {
+            enc: dummy::AudioEnc { delay: 576 },
+            capabilities: CodecCapabilities::empty(),
+        };
+
+        let mut pkt = Packet::new();
+        pkt.t.pts = Some(1000);
+        pkt.t.timebase = Some(Rational64::new(1, 48000));
+        ctx.apply_codec_delay(&mut pkt).unwrap();
+        assert_eq!(pkt.t.pts, Some(424));
+
+        let mut pkt = Packet::new();
+        pkt.t.pts = Some(100);
+        pkt.t.timebase = Some(Rational64::new(1, 1000));
+        ctx.apply_codec_delay(&mut pkt).unwrap();
+        assert_eq!(pkt.t.pts, Some(88));
+
+        let mut pkt = Packet::new();
+        pkt.t.pts = Some(5);
+        pkt.t.timebase = Some(Rational64::new(1, 1000));
+        ctx.apply_codec_delay(&mut pkt).unwrap();
+        assert_eq!(pkt.t.pts, Some(0));
+    }
+
+    #[test]
+    fn supported_audio_params() {
+        let ctx = Context {
+            enc: dummy::AudioEnc { delay: 0 },
+            capabilities: CodecCapabilities::empty(),
+        };
+
+        assert_eq!(ctx.supported_sample_rates(), vec![48000]);
+        assert_eq!(
+            ctx.supported_channel_layouts(),
+            vec![ChannelMap::default_map(2)]
+        );
+
+        let codecs = Codecs::from_list(&[DUMMY_DESCR]);
+        let ctx = Context::by_name(&codecs, "dummy").unwrap();
+        assert!(ctx.supported_sample_rates().is_empty());
+        assert!(ctx.supported_channel_layouts().is_empty());
+    }
+
     #[test]
     fn lookup() {
         let codecs = Codecs::from_list(&[DUMMY_DESCR]);
 
         let _enc = codecs.by_name("dummy");
     }
+
+    #[test]
+    fn set_threads() {
+        let codecs = Codecs::from_list(&[DUMMY_DESCR]);
+        let mut ctx = Context::by_name(&codecs, "dummy").unwrap();
+
+        ctx.set_threads(4).unwrap();
+        assert_eq!(ctx.encoder().threads, 4);
+
+        let mut ctx = Context {
+            enc: dummy::AudioEnc { delay: 0 },
+            capabilities: CodecCapabilities::empty(),
+        };
+        assert!(matches!(ctx.set_threads(4), Err(Error::Unsupported(_))));
+    }
 }
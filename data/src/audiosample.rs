@@ -3,6 +3,8 @@
 use std::fmt;
 use std::string::*;
 
+use num_derive::{FromPrimitive, ToPrimitive};
+
 /// Audio format definition.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Soniton {
@@ -34,6 +36,11 @@ fn round_to_byte(v: usize) -> usize {
     (v + 7) >> 3
 }
 
+/// Same as `round_to_byte`, returning `None` on overflow.
+fn round_to_byte_checked(v: usize) -> Option<usize> {
+    v.checked_add(7).map(|v| v >> 3)
+}
+
 impl Soniton {
     /// Constructs a new audio format definition.
     pub fn new(bits: u8, be: bool, packed: bool, planar: bool, float: bool, signed: bool) -> Self {
@@ -58,6 +65,69 @@ impl Soniton {
 
         align(s, alignment)
     }
+
+    /// Returns the amount of bytes needed to store
+    /// the audio of requested length (in samples).
+    ///
+    /// If the computation overflows, `None` is returned.
+    pub fn try_get_audio_size(self, length: usize, alignment: usize) -> Option<usize> {
+        let bits = self.bits as usize;
+        let s = if self.packed {
+            round_to_byte_checked(length.checked_mul(bits)?)?
+        } else {
+            length.checked_mul(round_to_byte(bits))?
+        };
+
+        s.checked_add(alignment - 1).map(|v| v & !(alignment - 1))
+    }
+
+    /// Tells whether `other` can represent all the sample values of
+    /// the current format without loss.
+    ///
+    /// Only the sample type is considered, not its layout.
+    pub fn is_lossless_to(&self, other: &Soniton) -> bool {
+        // Integers exactly representable by a float: mantissa bits plus sign
+        fn float_int_bits(bits: u8) -> u8 {
+            match bits {
+                32 => 24,
+                64 => 53,
+                _ => 0,
+            }
+        }
+
+        match (self.float, other.float) {
+            (true, true) => other.bits >= self.bits,
+            (true, false) => false,
+            (false, true) => {
+                let magnitude = if self.signed {
+                    self.bits - 1
+                } else {
+                    self.bits
+                };
+                magnitude <= float_int_bits(other.bits)
+            }
+            (false, false) => match (self.signed, other.signed) {
+                (false, true) => other.bits > self.bits,
+                (true, false) => false,
+                _ => other.bits >= self.bits,
+            },
+        }
+    }
+
+    /// Returns the number of whole samples stored in `bytes` bytes.
+    pub fn samples_from_bytes(self, bytes: usize) -> usize {
+        let bits = self.bits as usize;
+        if bits == 0 {
+            return 0;
+        }
+
+        if self.packed {
+            // Computes bytes * 8 / bits without overflowing
+            bytes / bits * 8 + bytes % bits * 8 / bits
+        } else {
+            bytes / round_to_byte(bits)
+        }
+    }
 }
 
 impl fmt::Display for Soniton {
@@ -79,7 +149,7 @@ impl fmt::Display for Soniton {
 }
 
 /// Known audio channel types.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum ChannelType {
     /// Center front.
@@ -234,6 +304,43 @@ impl fmt::Display for ChannelType {
     }
 }
 
+/// Standard channel layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelLayout {
+    /// Single center channel.
+    Mono,
+    /// Left and right channels.
+    Stereo,
+    /// Stereo plus a Low Frequency Effect channel.
+    TwoPointOne,
+    /// Left and right front and surround channels.
+    Quad,
+    /// Front, center, surround and Low Frequency Effect channels.
+    FivePointOne,
+    /// 5.1 layout plus side surround channels.
+    SevenPointOne,
+}
+
+impl ChannelLayout {
+    /// Returns all the standard layouts.
+    pub fn all() -> &'static [ChannelLayout] {
+        use self::ChannelLayout::*;
+        &[Mono, Stereo, TwoPointOne, Quad, FivePointOne, SevenPointOne]
+    }
+
+    fn channels(self) -> &'static [ChannelType] {
+        use self::ChannelType::*;
+        match self {
+            ChannelLayout::Mono => &[C],
+            ChannelLayout::Stereo => &[L, R],
+            ChannelLayout::TwoPointOne => &[L, R, LFE],
+            ChannelLayout::Quad => &[L, R, Ls, Rs],
+            ChannelLayout::FivePointOne => &[L, R, C, LFE, Ls, Rs],
+            ChannelLayout::SevenPointOne => &[L, R, C, LFE, Ls, Rs, Lss, Rss],
+        }
+    }
+}
+
 /// An ordered sequence of channels.
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub struct ChannelMap {
@@ -304,6 +411,42 @@ impl ChannelMap {
 
         ChannelMap { ids }
     }
+
+    /// Creates a channel map from a standard layout, in its canonical
+    /// channel order.
+    pub fn from_layout(layout: ChannelLayout) -> Self {
+        ChannelMap {
+            ids: layout.channels().to_vec(),
+        }
+    }
+
+    /// Returns the standard layout made of the channels of the map,
+    /// in any order, if any.
+    pub fn layout(&self) -> Option<ChannelLayout> {
+        ChannelLayout::all().iter().copied().find(|l| {
+            let channels = l.channels();
+            channels.len() == self.ids.len() && channels.iter().all(|c| self.ids.contains(c))
+        })
+    }
+}
+
+/// Converts unsigned 8-bit samples to signed 16-bit samples.
+///
+/// Only the first `min(src.len(), dst.len())` samples are converted.
+pub fn u8_to_s16(src: &[u8], dst: &mut [i16]) {
+    for (d, &s) in dst.iter_mut().zip(src) {
+        *d = (s as i16 - 128) << 8;
+    }
+}
+
+/// Converts signed 16-bit samples to unsigned 8-bit samples,
+/// rounding to the nearest value.
+///
+/// Only the first `min(src.len(), dst.len())` samples are converted.
+pub fn s16_to_u8(src: &[i16], dst: &mut [u8]) {
+    for (d, &s) in dst.iter_mut().zip(src) {
+        *d = ((s as i32 + 0x8080) >> 8).min(255) as u8;
+    }
 }
 
 /// A set of default constant channels for general use.
@@ -350,6 +493,16 @@ pub mod formats {
         signed: true,
     };
 
+    /// Predefined format for planar floating points 32-bit signed audio.
+    pub const F32P: Soniton = Soniton {
+        bits: 32,
+        be: false,
+        packed: false,
+        planar: true,
+        float: true,
+        signed: true,
+    };
+
     /// Predefined format for interleaved floating points 64-bit signed audio.
     pub const F64: Soniton = Soniton {
         bits: 64,
@@ -359,16 +512,114 @@ pub mod formats {
         float: true,
         signed: true,
     };
+
+    /// Returns all the predefined formats.
+    pub fn all() -> &'static [Soniton] {
+        &[U8, S16, S32, F32, F32P, F64]
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn u8_s16() {
+        let src = [0u8, 1, 127, 128, 129, 255];
+        let mut s16 = [0i16; 6];
+        u8_to_s16(&src, &mut s16);
+        assert_eq!(s16, [-32768, -32512, -256, 0, 256, 32512]);
+
+        let mut u8s = [0u8; 6];
+        s16_to_u8(&s16, &mut u8s);
+        assert_eq!(u8s, src);
+
+        s16_to_u8(&[i16::MIN, -129, -128, 127, 128, i16::MAX], &mut u8s);
+        assert_eq!(u8s, [0, 127, 128, 128, 129, 255]);
+    }
+
     #[test]
     fn fmt() {
         println!("{}", formats::S16);
         println!("{}", formats::U8);
         println!("{}", formats::F32);
     }
+
+    #[test]
+    fn audio_size() {
+        let s20 = Soniton::new(20, false, true, false, false, true);
+
+        assert_eq!(s20.try_get_audio_size(3, 1), Some(8));
+        assert_eq!(s20.try_get_audio_size(usize::MAX, 1), None);
+        assert_eq!(formats::S16.try_get_audio_size(5, 4), Some(12));
+        assert_eq!(formats::S16.try_get_audio_size(usize::MAX, 1), None);
+        assert_eq!(
+            formats::S16.try_get_audio_size(usize::MAX / 2, 1),
+            Some(usize::MAX - 1)
+        );
+    }
+
+    #[test]
+    fn lossless() {
+        use self::formats::*;
+        let s24 = Soniton::new(24, false, false, false, false, true);
+
+        assert!(S16.is_lossless_to(&S32));
+        assert!(S16.is_lossless_to(&F32));
+        assert!(U8.is_lossless_to(&S16));
+        assert!(S16.is_lossless_to(&S16));
+        assert!(s24.is_lossless_to(&F32P));
+        assert!(S32.is_lossless_to(&F64));
+        assert!(F32.is_lossless_to(&F64));
+        assert!(!F32.is_lossless_to(&S16));
+        assert!(!S32.is_lossless_to(&F32));
+        assert!(!S16.is_lossless_to(&U8));
+        assert!(!U8.is_lossless_to(&Soniton::new(8, false, false, false, false, true)));
+        assert!(!F64.is_lossless_to(&F32));
+    }
+
+    #[test]
+    fn samples_from_bytes() {
+        let s20 = Soniton::new(20, false, true, false, false, true);
+
+        assert_eq!(s20.samples_from_bytes(5), 2);
+        assert_eq!(s20.samples_from_bytes(8), 3);
+        assert_eq!(s20.samples_from_bytes(s20.get_audio_size(1000, 1)), 1000);
+        assert_eq!(formats::S16.samples_from_bytes(7), 3);
+        assert_eq!(formats::S16.samples_from_bytes(2048), 1024);
+        assert_eq!(
+            formats::S16.samples_from_bytes(formats::S16.get_audio_size(480, 1)),
+            480
+        );
+    }
+
+    #[test]
+    fn channel_layout() {
+        for &layout in ChannelLayout::all() {
+            let map = ChannelMap::from_layout(layout);
+            assert_eq!(map.layout(), Some(layout));
+        }
+
+        let map = ChannelMap::from_layout(ChannelLayout::FivePointOne);
+        assert_eq!(map.len(), 6);
+        assert_eq!(map.find_channel_id(ChannelType::LFE), Some(3));
+        assert_eq!(
+            ChannelMap::default_map(2).layout(),
+            Some(ChannelLayout::Stereo)
+        );
+        assert_eq!(
+            ChannelMap::default_map(1).layout(),
+            Some(ChannelLayout::Mono)
+        );
+        let mut map = ChannelMap::new();
+        map.add_channels(&[ChannelType::L, ChannelType::L]);
+        assert_eq!(map.layout(), None);
+        assert_eq!(ChannelMap::new().layout(), None);
+    }
+
+    #[test]
+    fn all() {
+        assert!(formats::all().contains(&formats::S16));
+        assert!(!formats::all().is_empty());
+    }
 }
@@ -10,8 +10,12 @@ use std::sync::Arc;
 use byte_slice_cast::*;
 use bytes::BytesMut;
 
+use av_bitstream::crc::Crc;
+
 use crate::audiosample::*;
+use crate::params;
 use crate::pixel::*;
+use crate::rational::Rational64;
 use crate::timeinfo::*;
 
 use self::FrameError::*;
@@ -23,6 +27,8 @@ pub enum FrameError {
     InvalidIndex,
     /// Invalid frame conversion.
     InvalidConversion,
+    /// Frame dimensions out of the allowed limits.
+    InvalidDimensions,
 }
 
 impl std::error::Error for FrameError {}
@@ -32,6 +38,7 @@ impl fmt::Display for FrameError {
         match self {
             InvalidIndex => write!(f, "Invalid Index"),
             InvalidConversion => write!(f, "Invalid Conversion"),
+            InvalidDimensions => write!(f, "Invalid Dimensions"),
         }
     }
 }
@@ -83,6 +90,10 @@ pub struct VideoInfo {
     pub format: Arc<Formaton>,
     /// Declared bits per sample.
     pub bits: u8,
+    /// Width of the region meant to be displayed.
+    pub display_width: usize,
+    /// Height of the region meant to be displayed.
+    pub display_height: usize,
 }
 
 impl VideoInfo {
@@ -102,9 +113,46 @@ impl VideoInfo {
             frame_type,
             format,
             bits,
+            display_width: width,
+            display_height: height,
         }
     }
 
+    /// Constructs a new `VideoInfo` instance, rejecting the dimensions
+    /// out of the given limits.
+    ///
+    /// See `within_limits`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_checked(
+        width: usize,
+        height: usize,
+        flipped: bool,
+        frame_type: FrameType,
+        format: Arc<Formaton>,
+        max_w: usize,
+        max_h: usize,
+        max_pixels: usize,
+    ) -> Result<Self, FrameError> {
+        let info = VideoInfo::new(width, height, flipped, frame_type, format);
+
+        if info.within_limits(max_w, max_h, max_pixels) {
+            Ok(info)
+        } else {
+            Err(InvalidDimensions)
+        }
+    }
+
+    /// Tells whether the frame is at most `max_w` pixels wide,
+    /// `max_h` pixels high and `max_pixels` pixels large.
+    pub fn within_limits(&self, max_w: usize, max_h: usize, max_pixels: usize) -> bool {
+        self.width <= max_w
+            && self.height <= max_h
+            && self
+                .width
+                .checked_mul(self.height)
+                .is_some_and(|pixels| pixels <= max_pixels)
+    }
+
     /// Returns frame width.
     pub fn get_width(&self) -> usize {
         self.width
@@ -135,6 +183,13 @@ impl VideoInfo {
         self.height = height;
     }
 
+    /// Sets the size of the region meant to be displayed, when smaller
+    /// than the coded frame size.
+    pub fn set_display_size(&mut self, width: usize, height: usize) {
+        self.display_width = width;
+        self.display_height = height;
+    }
+
     /// Returns video stream size with the specified alignment.
     pub fn size(&self, align: usize) -> usize {
         let mut size = 0;
@@ -193,6 +248,23 @@ impl AudioInfo {
             block_len,
         }
     }
+
+    /// Constructs a new `AudioInfo` instance from a standard channel layout.
+    pub fn simple(
+        samples: usize,
+        sample_rate: usize,
+        layout: ChannelLayout,
+        format: Soniton,
+    ) -> Self {
+        AudioInfo::new(
+            samples,
+            sample_rate,
+            ChannelMap::from_layout(layout),
+            Arc::new(format),
+            None,
+        )
+    }
+
     /// Returns audio sample rate.
     pub fn get_sample_rate(&self) -> usize {
         self.sample_rate
@@ -215,6 +287,15 @@ impl AudioInfo {
         self.block_len
     }
 
+    /// Returns one audio block duration in seconds.
+    ///
+    /// None if the block length or the sample rate is not present.
+    pub fn frame_duration(&self) -> Option<Rational64> {
+        self.block_len
+            .filter(|_| self.sample_rate > 0)
+            .map(|len| Rational64::new(len as i64, self.sample_rate as i64))
+    }
+
     /// Returns audio stream size with the specified alignment.
     pub fn size(&self, align: usize) -> usize {
         self.format.get_audio_size(self.samples, align) * self.map.len()
@@ -247,6 +328,8 @@ pub enum MediaKind {
     Video(VideoInfo),
     /// Audio codec information.
     Audio(AudioInfo),
+    /// Generic data (e.g. subtitles) with its length in bytes.
+    Data(usize),
 }
 
 impl MediaKind {
@@ -274,6 +357,18 @@ impl MediaKind {
     pub fn is_audio(&self) -> bool {
         matches!(self, MediaKind::Audio(_))
     }
+    /// Reports whether the current stream is a generic data stream.
+    pub fn is_data(&self) -> bool {
+        matches!(self, MediaKind::Data(_))
+    }
+    /// Returns a short name describing the kind of stream.
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            MediaKind::Video(_) => "video",
+            MediaKind::Audio(_) => "audio",
+            MediaKind::Data(_) => "data",
+        }
+    }
 }
 
 impl fmt::Display for MediaKind {
@@ -281,6 +376,7 @@ impl fmt::Display for MediaKind {
         let ret = match self {
             MediaKind::Audio(fmt) => format!("{}", fmt),
             MediaKind::Video(fmt) => format!("{}", fmt),
+            MediaKind::Data(size) => format!("Data ({} bytes)", size),
         };
         write!(f, "{}", ret)
     }
@@ -318,6 +414,7 @@ mod private {
     pub trait Supported: FromByteSlice {}
     impl Supported for u8 {}
     impl Supported for i16 {}
+    impl Supported for u16 {}
     impl Supported for f32 {}
 }
 
@@ -342,6 +439,7 @@ pub trait FrameBufferConv<T: private::Supported>: FrameBuffer {
 
 impl FrameBufferConv<u8> for dyn FrameBuffer {}
 impl FrameBufferConv<i16> for dyn FrameBuffer {}
+impl FrameBufferConv<u16> for dyn FrameBuffer {}
 impl FrameBufferConv<f32> for dyn FrameBuffer {}
 
 /// A series of methods to copy the content of a frame from or to a buffer.
@@ -415,15 +513,16 @@ impl DefaultFrameBuffer {
                     buf,
                     planes: Vec::with_capacity(video.format.get_num_comp()),
                 };
-                for &component in video.format.iter() {
-                    if let Some(c) = component {
-                        let planesize = c.get_data_size(video.width, video.height, ALIGNMENT);
-                        let linesize = c.get_linesize(video.width, ALIGNMENT);
-                        buffer.planes.push(Plane {
-                            buf: buffer.buf.split_to(planesize),
-                            linesize,
-                        });
-                    }
+                // Planar components are stored in the plane given by their offset
+                let mut components: Vec<_> = video.format.iter().flatten().collect();
+                components.sort_by_key(|c| if c.is_packed() { 0 } else { c.get_offset() });
+                for c in components {
+                    let planesize = c.get_data_size(video.width, video.height, ALIGNMENT);
+                    let linesize = c.get_linesize(video.width, ALIGNMENT);
+                    buffer.planes.push(Plane {
+                        buf: buffer.buf.split_to(planesize),
+                        linesize,
+                    });
                 }
                 buffer
             }
@@ -454,10 +553,32 @@ impl DefaultFrameBuffer {
                 }
                 buffer
             }
+            MediaKind::Data(size) => DefaultFrameBuffer {
+                buf: BytesMut::new(),
+                planes: vec![Plane {
+                    buf: BytesMut::zeroed(size),
+                    linesize: size,
+                }],
+            },
         }
     }
 }
 
+/// A view over a single frame plane along with its geometry.
+#[derive(Clone, Copy, Debug)]
+pub struct PlaneView<'a> {
+    /// Plane data, including the linesize padding.
+    pub data: &'a [u8],
+    /// Linesize (stride) of the plane in bytes.
+    pub linesize: usize,
+    /// Size in bytes of a row of the active region of the plane.
+    pub width: usize,
+    /// Number of rows of the plane.
+    pub height: usize,
+    /// Component stored in the plane, for video frames only.
+    pub component: Option<Chromaton>,
+}
+
 /// Decoded frame information.
 #[derive(Debug)]
 pub struct Frame {
@@ -486,6 +607,557 @@ impl Frame {
     }
 }
 
+// Returns the component stored in the idx-th plane of a video frame.
+//
+// The offset of a planar component is the index of its plane, the
+// components of packed formats are stored in the planes in order.
+fn plane_component(format: &Formaton, idx: usize) -> Option<Chromaton> {
+    format
+        .iter()
+        .flatten()
+        .find(|c| !c.is_packed() && c.get_offset() as usize == idx)
+        .copied()
+        .or_else(|| format.get_chromaton(idx).filter(|c| c.is_packed()))
+}
+
+impl Frame {
+    /// Returns the size in bytes of a row and the number of rows
+    /// of the active region of the idx-th video plane.
+    fn plane_geometry(&self, idx: usize) -> Option<(usize, usize)> {
+        if let MediaKind::Video(ref video) = self.kind {
+            let c = plane_component(&video.format, idx)?;
+            let bytes = (c.get_depth() as usize + 7) >> 3;
+            Some((c.get_width(video.width) * bytes, c.get_height(video.height)))
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over the frame planes along with their geometry.
+    ///
+    /// Audio planes are single rows holding the samples of one channel,
+    /// or of all the channels when the format is interleaved.
+    pub fn planes(&self) -> impl Iterator<Item = PlaneView<'_>> {
+        (0..self.buf.count()).filter_map(move |idx| {
+            let data = self.buf.as_slice_inner(idx).ok()?;
+            let linesize = self.buf.linesize(idx).ok()?;
+            let (width, height, component) = match self.kind {
+                MediaKind::Video(ref video) => {
+                    let (width, height) = self.plane_geometry(idx)?;
+                    (width, height, plane_component(&video.format, idx))
+                }
+                MediaKind::Audio(ref audio) => {
+                    let mut width = audio.format.get_audio_size(audio.samples, 1);
+                    if !audio.format.planar {
+                        width *= audio.map.len();
+                    }
+                    (width, 1, None)
+                }
+                MediaKind::Data(size) => (size, 1, None),
+            };
+
+            Some(PlaneView {
+                data,
+                linesize,
+                width,
+                height,
+                component,
+            })
+        })
+    }
+
+    /// Returns a tightly-packed copy of the active region of the idx-th plane.
+    ///
+    /// Video planes are copied row by row without the linesize padding,
+    /// audio planes without the trailing alignment padding.
+    pub fn plane_to_vec(&self, idx: usize) -> Result<Vec<u8>, FrameError> {
+        let src = self.buf.as_slice_inner(idx)?;
+
+        match self.kind {
+            MediaKind::Video(_) => {
+                let (width, height) = self.plane_geometry(idx).ok_or(InvalidIndex)?;
+                let linesize = self.buf.linesize(idx)?;
+                let mut dst = vec![0; width * height];
+                if dst.is_empty() {
+                    return Ok(dst);
+                }
+
+                copy_plane(&mut dst, width, src, linesize, width, height);
+
+                Ok(dst)
+            }
+            MediaKind::Audio(ref audio) => {
+                let mut size = audio.format.get_audio_size(audio.samples, 1);
+                if !audio.format.planar {
+                    size *= audio.map.len();
+                }
+
+                Ok(src[..size.min(src.len())].to_vec())
+            }
+            MediaKind::Data(_) => Ok(src.to_vec()),
+        }
+    }
+
+    /// Computes the MPEG-2 CRC-32 of the active region of the idx-th plane,
+    /// as copied by `plane_to_vec`.
+    ///
+    /// The linesize padding is ignored, so frames with the same content
+    /// have the same checksums regardless of their memory layout.
+    pub fn plane_crc32(&self, idx: usize) -> Result<u32, FrameError> {
+        let mut crc = Crc::crc32_mpeg();
+
+        if let MediaKind::Video(_) = self.kind {
+            let (width, height) = self.plane_geometry(idx).ok_or(InvalidIndex)?;
+            let linesize = self.buf.linesize(idx)?;
+            let src = self.buf.as_slice_inner(idx)?;
+            if width > 0 {
+                for line in src.chunks(linesize).take(height) {
+                    crc.update(&line[..width]);
+                }
+            }
+        } else {
+            crc.update(&self.plane_to_vec(idx)?);
+        }
+
+        Ok(crc.sum())
+    }
+
+    /// Creates an independent copy of the frame, duplicating its buffer.
+    ///
+    /// The new frame uses a default frame buffer, so its linesizes may
+    /// differ from the original ones.
+    pub fn deep_clone(&self) -> Frame {
+        let mut frame = Frame::new_default_frame(self.kind.clone(), Some(self.t.clone()));
+
+        for i in 0..self.buf.count().min(frame.buf.count()) {
+            let src = self.buf.as_slice_inner(i).unwrap();
+            let src_linesize = self.buf.linesize(i).unwrap();
+            let dst_linesize = frame.buf.linesize(i).unwrap();
+            let dst = frame.buf.as_mut_slice_inner(i).unwrap();
+
+            match self.plane_geometry(i) {
+                Some((width, height)) if width > 0 => {
+                    copy_plane(dst, dst_linesize, src, src_linesize, width, height);
+                }
+                Some(_) => {}
+                None => {
+                    let len = src.len().min(dst.len());
+                    dst[..len].copy_from_slice(&src[..len]);
+                }
+            }
+        }
+
+        frame
+    }
+
+    /// Returns the largest power-of-two alignment, in bytes, of the start
+    /// of the idx-th plane.
+    pub fn plane_alignment(&self, idx: usize) -> Result<usize, FrameError> {
+        let addr = self.buf.as_slice_inner(idx)?.as_ptr() as usize;
+
+        Ok(1 << addr.trailing_zeros())
+    }
+
+    /// Tells whether any plane of the frame shares memory with
+    /// any plane of `other`.
+    pub fn aliases(&self, other: &Frame) -> bool {
+        fn ranges(frame: &Frame) -> impl Iterator<Item = (usize, usize)> + '_ {
+            (0..frame.buf.count())
+                .filter_map(|i| frame.buf.as_slice_inner(i).ok())
+                .filter(|data| !data.is_empty())
+                .map(|data| {
+                    let start = data.as_ptr() as usize;
+                    (start, start + data.len())
+                })
+        }
+
+        ranges(self).any(|(start, end)| {
+            ranges(other).any(|(other_start, other_end)| start < other_end && other_start < end)
+        })
+    }
+
+    /// Zeroes the bytes between the active width and the linesize
+    /// on every row of each video plane.
+    ///
+    /// It does nothing on non-video frames.
+    pub fn clear_padding(&mut self) {
+        for idx in 0..self.buf.count() {
+            let (width, linesize) = match (self.plane_geometry(idx), self.buf.linesize(idx)) {
+                (Some((width, _)), Ok(linesize)) if linesize > width => (width, linesize),
+                _ => continue,
+            };
+
+            if let Ok(data) = self.buf.as_mut_slice_inner(idx) {
+                for line in data.chunks_mut(linesize) {
+                    if line.len() > width {
+                        line[width..].fill(0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Swaps the bytes of every 16-bit element of each video plane,
+    /// e.g. to turn big-endian samples into little-endian ones.
+    ///
+    /// The frame format is not checked nor updated, so the caller
+    /// decides when the swap is needed.
+    /// It does nothing on non-video frames.
+    pub fn swap_bytes_16(&mut self) {
+        if !self.kind.is_video() {
+            return;
+        }
+
+        for idx in 0..self.buf.count() {
+            if let Ok(data) = self.buf.as_mut_slice_inner(idx) {
+                for pair in data.chunks_exact_mut(2) {
+                    pair.swap(0, 1);
+                }
+            }
+        }
+    }
+
+    /// Returns the number of significant bits of the frame samples.
+    ///
+    /// It is the largest component depth for video and the sample depth
+    /// for audio, regardless of the storage size. Data frames are bytes.
+    pub fn bit_depth(&self) -> u8 {
+        match self.kind {
+            MediaKind::Video(ref video) => video
+                .format
+                .iter()
+                .flatten()
+                .map(|c| c.get_depth())
+                .max()
+                .unwrap_or(0),
+            MediaKind::Audio(ref audio) => audio.format.bits,
+            MediaKind::Data(_) => 8,
+        }
+    }
+
+    /// Converts the frame timestamps to the timebase `to`.
+    ///
+    /// See `TimeInfo::rescale`.
+    pub fn rescale_timestamps(&mut self, to: Rational64) {
+        self.t.rescale(to);
+    }
+
+    /// Returns the `(x, y, width, height)` rectangle of the frame
+    /// meant to be displayed.
+    ///
+    /// Returns `None` if the frame is not a video frame.
+    pub fn active_region(&self) -> Option<(usize, usize, usize, usize)> {
+        if let MediaKind::Video(ref video) = self.kind {
+            Some((
+                0,
+                0,
+                video.display_width.min(video.width),
+                video.display_height.min(video.height),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Converts the frame to a thumbnail made of tightly-packed RGBA pixels,
+    /// returned along with its width and height.
+    ///
+    /// The frame is scaled down with nearest neighbour sampling to fit into
+    /// `max_w`x`max_h` while preserving its aspect ratio.
+    ///
+    /// Only 8-bit planar YUV and RGB formats and packed RGB formats with
+    /// 8-bit components are supported.
+    pub fn to_rgba_thumbnail(
+        &self,
+        max_w: usize,
+        max_h: usize,
+    ) -> Result<(Vec<u8>, usize, usize), FrameError> {
+        let video = match self.kind {
+            MediaKind::Video(ref video) => video,
+            _ => return Err(InvalidConversion),
+        };
+        let format = &video.format;
+        let (width, height) = (video.width, video.height);
+
+        let yuv = match format.get_model() {
+            ColorModel::Trichromatic(TrichromaticEncodingSystem::YUV(YUVSystem::YCbCr(range))) => {
+                Some(range)
+            }
+            ColorModel::Trichromatic(TrichromaticEncodingSystem::RGB) => None,
+            _ => return Err(InvalidConversion),
+        };
+        let comps = format.get_num_comp();
+        let bytes_8bit = format
+            .iter()
+            .flatten()
+            .all(|c| c.get_depth() == 8 && c.get_shift() == 0);
+        if !bytes_8bit
+            || format.is_paletted()
+            || comps < 3
+            || width == 0
+            || height == 0
+            || max_w == 0
+            || max_h == 0
+        {
+            return Err(InvalidConversion);
+        }
+
+        let (tw, th) = if width <= max_w && height <= max_h {
+            (width, height)
+        } else if width * max_h > height * max_w {
+            (max_w, (height * max_w / width).max(1))
+        } else {
+            ((width * max_h / height).max(1), max_h)
+        };
+
+        let has_alpha = format.has_alpha() && comps > 3;
+        // The offset of a planar component is the index of its plane,
+        // packed components are interleaved in the first plane
+        let mut planes = Vec::with_capacity(4);
+        for c in format.iter().flatten().take(4) {
+            let (h_ss, v_ss) = c.get_subsampling();
+            let (idx, step, offset) = if c.is_packed() {
+                (0, c.get_step() as usize, c.get_offset() as usize)
+            } else {
+                (c.get_offset() as usize, 1, 0)
+            };
+            let data = self.buf.as_slice_inner(idx)?;
+            let linesize = self.buf.linesize(idx)?;
+            let last = linesize * (c.get_height(height) - 1) + (c.get_width(width) - 1) * step;
+            if data.len() <= last + offset {
+                return Err(InvalidConversion);
+            }
+            planes.push((&data[offset..], linesize, h_ss, v_ss, step));
+        }
+
+        let (kr, kb) = if format.get_matrix() == MatrixCoefficients::BT709 {
+            (0.2126, 0.0722)
+        } else {
+            (0.299, 0.114)
+        };
+
+        let mut out = Vec::with_capacity(tw * th * 4);
+        for ty in 0..th {
+            let y = ty * height / th;
+            for tx in 0..tw {
+                let x = tx * width / tw;
+                let sample = |i: usize| {
+                    let (data, linesize, h_ss, v_ss, step) = planes[i];
+                    f32::from(data[(y >> v_ss) * linesize + (x >> h_ss) * step])
+                };
+
+                let (r, g, b) = match yuv {
+                    Some(range) => {
+                        let (l, cb, cr) = match range {
+                            YUVRange::Full => (sample(0), sample(1) - 128.0, sample(2) - 128.0),
+                            YUVRange::Limited => (
+                                (sample(0) - 16.0) * 255.0 / 219.0,
+                                (sample(1) - 128.0) * 255.0 / 224.0,
+                                (sample(2) - 128.0) * 255.0 / 224.0,
+                            ),
+                        };
+                        let r = l + 2.0 * (1.0 - kr) * cr;
+                        let b = l + 2.0 * (1.0 - kb) * cb;
+                        let g = (l - kr * r - kb * b) / (1.0 - kr - kb);
+                        (r, g, b)
+                    }
+                    None => (sample(0), sample(1), sample(2)),
+                };
+                let a = if has_alpha { sample(3) } else { 255.0 };
+
+                out.extend(
+                    [r, g, b, a]
+                        .iter()
+                        .map(|v| v.round().clamp(0.0, 255.0) as u8),
+                );
+            }
+        }
+
+        Ok((out, tw, th))
+    }
+
+    /// Returns the number of audio samples per channel the frame buffer
+    /// can hold.
+    ///
+    /// Returns `None` if the frame is not an audio frame.
+    pub fn sample_capacity(&self) -> Option<usize> {
+        if let MediaKind::Audio(ref audio) = self.kind {
+            let mut bytes = self.buf.linesize(0).unwrap_or(0);
+            if !audio.format.planar {
+                bytes /= audio.map.len().max(1);
+            }
+            Some(audio.format.samples_from_bytes(bytes))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the number of valid audio samples per channel, e.g. when the
+    /// last frame of a stream is shorter than the allocated one.
+    ///
+    /// The buffer is kept untouched, so `n` is clamped to its capacity.
+    /// It does nothing on non-audio frames.
+    pub fn set_valid_samples(&mut self, n: usize) {
+        if let Some(capacity) = self.sample_capacity() {
+            if let MediaKind::Audio(ref mut audio) = self.kind {
+                audio.samples = n.min(capacity);
+            }
+        }
+    }
+
+    /// Downmixes a 32-bit float audio frame to stereo, using the ITU-R
+    /// BS.775 coefficients: the center and surround channels are added
+    /// to the front ones attenuated by 3 dB, the LFE channel is dropped.
+    ///
+    /// The source channels are found through the frame channel map,
+    /// the output samples are clamped to [-1, 1].
+    pub fn downmix_to_stereo(&self) -> Result<Frame, FrameError> {
+        use std::f32::consts::FRAC_1_SQRT_2 as K;
+        use ChannelType::*;
+
+        let audio = match self.kind {
+            MediaKind::Audio(ref audio) if audio.format.float && audio.format.bits == 32 => audio,
+            _ => return Err(InvalidConversion),
+        };
+        let gains = (0..audio.map.len())
+            .map(|idx| match audio.map.get_channel(idx) {
+                L => Ok((1.0, 0.0)),
+                R => Ok((0.0, 1.0)),
+                C => Ok((K, K)),
+                Ls | Lss => Ok((K, 0.0)),
+                Rs | Rss => Ok((0.0, K)),
+                LFE => Ok((0.0, 0.0)),
+                _ => Err(InvalidConversion),
+            })
+            .collect::<Result<Vec<(f32, f32)>, _>>()?;
+
+        let channels = gains.len();
+        let planar = audio.format.planar;
+        let samples = audio.samples;
+        let mut left = vec![0.0; samples];
+        let mut right = vec![0.0; samples];
+
+        for (ch, &(gl, gr)) in gains.iter().enumerate() {
+            let (src, offset, step): (&[f32], usize, usize) = if planar {
+                (self.buf.as_slice(ch)?, 0, 1)
+            } else {
+                (self.buf.as_slice(0)?, ch, channels)
+            };
+            let src = src.iter().skip(offset).step_by(step).take(samples);
+
+            for ((l, r), &v) in left.iter_mut().zip(right.iter_mut()).zip(src) {
+                *l += v * gl;
+                *r += v * gr;
+            }
+        }
+
+        let info = AudioInfo::new(
+            samples,
+            audio.sample_rate,
+            ChannelMap::from_layout(ChannelLayout::Stereo),
+            audio.format.clone(),
+            audio.block_len,
+        );
+        let mut frame = Frame::new_default_frame(info, Some(self.t.clone()));
+
+        if planar {
+            for (idx, data) in [left, right].iter().enumerate() {
+                let dst: &mut [f32] = frame.buf.as_mut_slice(idx)?;
+                for (d, &v) in dst.iter_mut().zip(data) {
+                    *d = v.clamp(-1.0, 1.0);
+                }
+            }
+        } else {
+            let dst: &mut [f32] = frame.buf.as_mut_slice(0)?;
+            for (d, (&l, &r)) in dst.chunks_exact_mut(2).zip(left.iter().zip(&right)) {
+                d[0] = l.clamp(-1.0, 1.0);
+                d[1] = r.clamp(-1.0, 1.0);
+            }
+        }
+
+        Ok(frame)
+    }
+
+    fn plane_diff<F>(&self, other: &Frame, plane: usize, f: F) -> Option<u64>
+    where
+        F: Fn(u64) -> u64,
+    {
+        let is_8bit = |frame: &Frame| match frame.kind {
+            MediaKind::Video(ref video) => {
+                video.format.iter().flatten().all(|c| c.get_depth() == 8)
+            }
+            _ => false,
+        };
+        if !is_8bit(self) || !is_8bit(other) {
+            return None;
+        }
+
+        let (width, height) = self.plane_geometry(plane)?;
+        if other.plane_geometry(plane)? != (width, height) {
+            return None;
+        }
+
+        let src = self.buf.as_slice_inner(plane).ok()?;
+        let src_linesize = self.buf.linesize(plane).ok()?;
+        let ref_src = other.buf.as_slice_inner(plane).ok()?;
+        let ref_linesize = other.buf.linesize(plane).ok()?;
+
+        let sum = src
+            .chunks(src_linesize)
+            .zip(ref_src.chunks(ref_linesize))
+            .take(height)
+            .flat_map(|(a, b)| a[..width].iter().zip(&b[..width]))
+            .map(|(&a, &b)| f(u64::from(a.abs_diff(b))))
+            .sum();
+
+        Some(sum)
+    }
+
+    /// Computes the sum of absolute differences between the active regions
+    /// of the idx-th plane of two 8-bit video frames.
+    ///
+    /// Returns `None` if the frames are not 8-bit video frames or
+    /// their plane dimensions do not match.
+    pub fn sad(&self, other: &Frame, plane: usize) -> Option<u64> {
+        self.plane_diff(other, plane, |d| d)
+    }
+
+    /// Computes the sum of squared differences between the active regions
+    /// of the idx-th plane of two 8-bit video frames.
+    ///
+    /// Returns `None` if the frames are not 8-bit video frames or
+    /// their plane dimensions do not match.
+    pub fn ssd(&self, other: &Frame, plane: usize) -> Option<u64> {
+        self.plane_diff(other, plane, |d| d * d)
+    }
+
+    /// Computes the peak signal-to-noise ratio in dB between two 8-bit video
+    /// frames, combining the squared differences of all their planes.
+    ///
+    /// Identical frames have an infinite PSNR, frames with components
+    /// deeper than 8 bits have none.
+    pub fn psnr(&self, other: &Frame) -> Option<f64> {
+        let mut ssd = 0;
+        let mut samples = 0;
+
+        for plane in 0..self.buf.count() {
+            let (width, height) = self.plane_geometry(plane)?;
+            ssd += self.ssd(other, plane)?;
+            samples += width * height;
+        }
+
+        if samples == 0 {
+            return None;
+        }
+        if ssd == 0 {
+            return Some(f64::INFINITY);
+        }
+
+        let mse = ssd as f64 / samples as f64;
+        Some(10.0 * (255.0 * 255.0 / mse).log10())
+    }
+}
+
 impl FrameBufferCopy for Frame {
     fn copy_plane_to_buffer(&self, plane_index: usize, dst: &mut [u8], dst_linesize: usize) {
         if let MediaKind::Video(ref fmt) = self.kind {
@@ -495,6 +1167,11 @@ impl FrameBufferCopy for Frame {
             let src_linesize = self.buf.linesize(plane_index).unwrap();
 
             copy_plane(dst, dst_linesize, src, src_linesize, width, height);
+        } else if let MediaKind::Data(size) = self.kind {
+            let src = self.buf.as_slice_inner(plane_index).unwrap();
+            let len = size.min(dst.len());
+
+            dst[..len].copy_from_slice(&src[..len]);
         } else {
             unimplemented!();
         }
@@ -505,20 +1182,15 @@ impl FrameBufferCopy for Frame {
         IM: Iterator<Item = &'a mut [u8]>,
         IU: Iterator<Item = usize>,
     {
-        if let MediaKind::Video(ref fmt) = self.kind {
-            let width = fmt.width;
-            let height = fmt.height;
-            let dst_iter = dst.zip(dst_linesizes);
-            let iter = dst_iter.zip(0..self.buf.count()).zip(fmt.format.iter());
-
-            for (((d, d_linesize), plane_index), c) in iter {
+        if let MediaKind::Video(_) = self.kind {
+            for ((d, d_linesize), plane) in dst.zip(dst_linesizes).zip(self.planes()) {
                 copy_plane(
                     d,
                     d_linesize,
-                    self.buf.as_slice_inner(plane_index).unwrap(),
-                    self.buf.linesize(plane_index).unwrap(),
-                    c.unwrap().get_width(width),
-                    c.unwrap().get_height(height),
+                    plane.data,
+                    plane.linesize,
+                    plane.width,
+                    plane.height,
                 );
             }
         } else {
@@ -552,6 +1224,12 @@ impl FrameBufferCopy for Frame {
                     cc.unwrap().get_height(height),
                 );
             }
+        } else if let MediaKind::Data(size) = self.kind {
+            let data = self.buf.as_mut_slice_inner(0).unwrap();
+            let ss = src.next().unwrap();
+            let len = size.min(ss.len());
+
+            data[..len].copy_from_slice(&ss[..len]);
         } else {
             unimplemented!();
         }
@@ -578,9 +1256,116 @@ fn copy_plane(
     }
 }
 
+/// A set of preallocated frames sharing the same media kind.
+#[derive(Debug)]
+pub struct FramePool {
+    kind: MediaKind,
+    frames: Vec<Frame>,
+}
+
+impl FramePool {
+    /// Creates a pool of `count` frames of the given kind.
+    pub fn new(kind: MediaKind, count: usize) -> Self {
+        let frames = (0..count)
+            .map(|_| Frame::new_default_frame(kind.clone(), None))
+            .collect();
+
+        FramePool { kind, frames }
+    }
+
+    /// Creates a pool of `count` frames from codec parameters.
+    ///
+    /// Only video parameters with a known pixel format describe a frame
+    /// completely, `None` is returned otherwise.
+    pub fn from_params(params: &params::CodecParams, count: usize) -> Option<Self> {
+        match params.kind {
+            Some(params::MediaKind::Video(ref video)) => {
+                let info = VideoInfo::new(
+                    video.width,
+                    video.height,
+                    false,
+                    FrameType::OTHER,
+                    video.format.clone()?,
+                );
+                Some(FramePool::new(info.into(), count))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the kind of the frames in the pool.
+    pub fn get_kind(&self) -> &MediaKind {
+        &self.kind
+    }
+
+    /// Returns the number of frames available in the pool.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Tells whether the pool has no frames available.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Takes a frame out of the pool, allocating a new one
+    /// if the pool is empty.
+    pub fn get_frame(&mut self) -> Frame {
+        self.frames
+            .pop()
+            .unwrap_or_else(|| Frame::new_default_frame(self.kind.clone(), None))
+    }
+
+    /// Puts a frame back into the pool.
+    ///
+    /// Frames of a different kind are dropped.
+    pub fn put_frame(&mut self, frame: Frame) {
+        if frame.kind == self.kind {
+            self.frames.push(frame);
+        }
+    }
+}
+
 /// A specialized type for reference-counted `Frame`
 pub type ArcFrame = Arc<Frame>;
 
+/// Returns the owned `Frame` if `frame` is its only reference,
+/// avoiding a copy to modify it.
+///
+/// If other references exist, the `ArcFrame` is returned as error.
+pub fn try_unwrap_frame(frame: ArcFrame) -> Result<Frame, ArcFrame> {
+    Arc::try_unwrap(frame)
+}
+
+/// Tells whether `frame` is its only reference, so that it can be
+/// modified in place.
+pub fn is_writable(frame: &ArcFrame) -> bool {
+    Arc::strong_count(frame) == 1 && Arc::weak_count(frame) == 0
+}
+
+/// Returns a mutable reference to the frame, replacing it with
+/// a deep copy first if other references to it exist.
+pub fn make_writable(frame: &mut ArcFrame) -> &mut Frame {
+    if !is_writable(frame) {
+        *frame = Arc::new(frame.deep_clone());
+    }
+
+    Arc::get_mut(frame).unwrap()
+}
+
+/// Returns the indices of a batch of frames in decode order,
+/// sorted in presentation order.
+///
+/// Frames sharing the same presentation timestamp keep their relative order,
+/// frames without one are placed first.
+pub fn presentation_order(frames: &[ArcFrame]) -> Vec<usize> {
+    let mut order = (0..frames.len()).collect::<Vec<_>>();
+
+    order.sort_by_key(|&i| frames[i].t.pts);
+
+    order
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -610,7 +1395,20 @@ mod test {
         assert!(!(info1 == info2));
     }
 
-    use crate::pixel::formats::{RGB565, YUV420};
+    #[test]
+    fn test_frame_duration() {
+        let map = ChannelMap::default_map(2);
+        let sn = Arc::new(formats::S16);
+        let info = AudioInfo::new(1024, 48000, map.clone(), sn.clone(), Some(1024));
+
+        assert_eq!(info.frame_duration(), Some(Rational64::new(1024, 48000)));
+
+        let info = AudioInfo::new(1024, 48000, map, sn, None);
+
+        assert_eq!(info.frame_duration(), None);
+    }
+
+    use crate::pixel::formats::{GBRP, PAL8, RGB24, RGB565, YUV420, YUV420_10};
 
     #[test]
     fn test_video_format_cmp() {
@@ -635,6 +1433,96 @@ mod test {
         assert!(!(info1 == info2));
     }
 
+    fn yuv420_frame(width: usize, height: usize, value: u8) -> Frame {
+        let fm = Arc::new(*YUV420);
+        let video_info = VideoInfo::new(width, height, false, FrameType::I, fm);
+        let mut frame = Frame::new_default_frame(video_info, None);
+
+        for i in 0..frame.buf.count() {
+            frame.buf.as_mut_slice_inner(i).unwrap().fill(value);
+        }
+
+        frame
+    }
+
+    #[test]
+    fn test_frame_diff() {
+        let frame1 = yuv420_frame(16, 16, 100);
+        let frame2 = yuv420_frame(16, 16, 100);
+
+        assert_eq!(frame1.sad(&frame2, 0), Some(0));
+        assert_eq!(frame1.ssd(&frame2, 1), Some(0));
+        assert_eq!(frame1.psnr(&frame2), Some(f64::INFINITY));
+
+        let frame3 = yuv420_frame(16, 16, 102);
+
+        assert_eq!(frame1.sad(&frame3, 0), Some(2 * 16 * 16));
+        assert_eq!(frame1.ssd(&frame3, 0), Some(4 * 16 * 16));
+        assert_eq!(frame1.sad(&frame3, 1), Some(2 * 8 * 8));
+        let psnr = frame1.psnr(&frame3).unwrap();
+        assert!((psnr - 10.0 * (255.0f64 * 255.0 / 4.0).log10()).abs() < 1e-9);
+
+        let frame4 = yuv420_frame(32, 16, 100);
+        assert_eq!(frame1.sad(&frame4, 0), None);
+        assert_eq!(frame1.psnr(&frame4), None);
+    }
+
+    #[test]
+    fn test_frame_diff_high_depth() {
+        use crate::pixel::formats::YUV420_10;
+
+        let info = VideoInfo::new(16, 16, false, FrameType::I, Arc::new(*YUV420_10));
+        let frame1 = Frame::new_default_frame(info, None);
+        let frame2 = frame1.deep_clone();
+
+        assert_eq!(frame1.sad(&frame2, 0), None);
+        assert_eq!(frame1.ssd(&frame2, 1), None);
+        assert_eq!(frame1.psnr(&frame2), None);
+        assert_eq!(yuv420_frame(16, 16, 0).sad(&frame1, 0), None);
+    }
+
+    #[test]
+    fn test_mixed_depth_planes() {
+        use crate::pixel::formats::{YUV420_10, YUV420_10_8};
+
+        let fm = Arc::new(*YUV420_10_8);
+        let video_info = VideoInfo::new(64, 64, false, FrameType::I, fm);
+        assert_eq!(video_info.size(32), 64 * 2 * 64 + 2 * 32 * 32);
+
+        let frame = Frame::new_default_frame(video_info, None);
+        assert_eq!(frame.buf.linesize(0), Ok(128));
+        assert_eq!(frame.buf.linesize(1), Ok(32));
+        assert_eq!(frame.buf.linesize(2), Ok(32));
+
+        let fm = Arc::new(*YUV420_10);
+        let video_info = VideoInfo::new(64, 64, false, FrameType::I, fm);
+        let frame = Frame::new_default_frame(video_info, None);
+        assert_eq!(frame.buf.linesize(0), Ok(128));
+        assert_eq!(frame.buf.linesize(1), Ok(64));
+    }
+
+    #[test]
+    fn test_plane_to_vec() {
+        let mut frame = yuv420_frame(42, 42, 0);
+        let linesize = frame.buf.linesize(0).unwrap();
+        assert!(linesize > 42);
+
+        let data = frame.buf.as_mut_slice_inner(0).unwrap();
+        for (y, row) in data.chunks_mut(linesize).enumerate() {
+            row[..42].fill(y as u8);
+            row[42..].fill(255);
+        }
+
+        let plane = frame.plane_to_vec(0).unwrap();
+        assert_eq!(plane.len(), 42 * 42);
+        for (y, row) in plane.chunks(42).enumerate() {
+            assert!(row.iter().all(|&v| v == y as u8));
+        }
+
+        assert_eq!(frame.plane_to_vec(1).unwrap().len(), 21 * 21);
+        assert_eq!(frame.plane_to_vec(3), Err(FrameError::InvalidIndex));
+    }
+
     #[test]
     #[should_panic]
     fn test_frame_copy_from_slice() {
@@ -649,4 +1537,485 @@ mod test {
             vec![40; 2].into_iter(),
         );
     }
+
+    #[test]
+    fn test_planes() {
+        let frame = yuv420_frame(20, 4, 0);
+        let planes: Vec<_> = frame.planes().collect();
+        assert_eq!(planes.len(), 3);
+
+        for (plane, (width, height)) in planes.iter().zip([(20, 4), (10, 2), (10, 2)]) {
+            assert_eq!((plane.width, plane.height), (width, height));
+            assert!(plane.linesize >= width);
+            assert!(plane.data.len() >= plane.linesize * height);
+            assert!(plane.component.is_some());
+        }
+
+        let info = VideoInfo::new(20, 4, false, FrameType::I, Arc::new(*YUV420_10));
+        let frame = Frame::new_default_frame(info, None);
+        let widths: Vec<_> = frame.planes().map(|p| p.width).collect();
+        assert_eq!(widths, [40, 20, 20]);
+
+        let info = VideoInfo::new(4, 4, false, FrameType::I, Arc::new(*GBRP));
+        let frame = Frame::new_default_frame(info, None);
+        let components: Vec<_> = frame.planes().map(|p| p.component.unwrap()).collect();
+        for (idx, c) in components.iter().enumerate() {
+            assert_eq!(c.get_offset() as usize, idx);
+        }
+        assert_eq!(components[0], GBRP.get_chromaton(1).unwrap());
+    }
+
+    #[test]
+    fn test_copy_frame_to_buffer_high_depth() {
+        let info = VideoInfo::new(4, 2, false, FrameType::I, Arc::new(*YUV420_10));
+        let mut frame = Frame::new_default_frame(info, None);
+        for idx in 0..3 {
+            frame.buf.as_mut_slice_inner(idx).unwrap().fill(0xab);
+        }
+
+        let mut planes = [vec![0u8; 16], vec![0u8; 4], vec![0u8; 4]];
+        frame.copy_frame_to_buffer(
+            planes.iter_mut().map(|p| p.as_mut_slice()),
+            [8, 4, 4].into_iter(),
+        );
+        assert!(planes.iter().all(|p| p.iter().all(|&v| v == 0xab)));
+    }
+
+    #[test]
+    fn test_clear_padding() {
+        let mut frame = yuv420_frame(20, 4, 0xff);
+        frame.clear_padding();
+
+        for (idx, width) in [(0, 20), (1, 10), (2, 10)] {
+            let linesize = frame.buf.linesize(idx).unwrap();
+            assert!(linesize > width);
+
+            let data = frame.buf.as_slice_inner(idx).unwrap();
+            for line in data.chunks(linesize) {
+                assert!(line[..width].iter().all(|&v| v == 0xff));
+                assert!(line[width..].iter().all(|&v| v == 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_active_region() {
+        let mut info = VideoInfo::new(1920, 1088, false, FrameType::I, Arc::new(*YUV420));
+        let frame = Frame::new_default_frame(info.clone(), None);
+        assert_eq!(frame.active_region(), Some((0, 0, 1920, 1088)));
+
+        info.set_display_size(1920, 1080);
+        let frame = Frame::new_default_frame(info, None);
+        assert_eq!(frame.active_region(), Some((0, 0, 1920, 1080)));
+        assert_eq!(frame.plane_to_vec(0).unwrap().len(), 1920 * 1088);
+
+        assert_eq!(
+            Frame::new_default_frame(MediaKind::Data(4), None).active_region(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rgba_thumbnail() {
+        let fill = |frame: &mut Frame, values: [u8; 3]| {
+            for (i, v) in values.into_iter().enumerate() {
+                frame.buf.as_mut_slice_inner(i).unwrap().fill(v);
+            }
+        };
+
+        let mut frame = yuv420_frame(64, 32, 0);
+        fill(&mut frame, [235, 128, 128]);
+        let (rgba, w, h) = frame.to_rgba_thumbnail(16, 16).unwrap();
+        assert_eq!((w, h), (16, 8));
+        assert_eq!(rgba, vec![255; 16 * 8 * 4]);
+
+        // Pure red in limited range BT.601
+        fill(&mut frame, [81, 90, 240]);
+        let (rgba, w, h) = frame.to_rgba_thumbnail(128, 128).unwrap();
+        assert_eq!((w, h), (64, 32));
+        for px in rgba.chunks(4) {
+            assert!(px[0] >= 254 && px[1] <= 1 && px[2] <= 1 && px[3] == 255);
+        }
+
+        let info = VideoInfo::new(8, 8, false, FrameType::I, Arc::new(*GBRP));
+        let mut frame = Frame::new_default_frame(info, None);
+        fill(&mut frame, [20, 30, 10]);
+        let (rgba, _, _) = frame.to_rgba_thumbnail(4, 4).unwrap();
+        assert_eq!(rgba, [10, 20, 30, 255].repeat(16));
+
+        let info = VideoInfo::new(8, 8, false, FrameType::I, Arc::new(*RGB24));
+        let mut frame = Frame::new_default_frame(info, None);
+        let linesize = frame.buf.linesize(0).unwrap();
+        for line in frame
+            .buf
+            .as_mut_slice_inner(0)
+            .unwrap()
+            .chunks_mut(linesize)
+        {
+            for px in line[..8 * 3].chunks_mut(3) {
+                px.copy_from_slice(&[10, 20, 30]);
+            }
+        }
+        let (rgba, w, h) = frame.to_rgba_thumbnail(4, 4).unwrap();
+        assert_eq!((w, h), (4, 4));
+        assert_eq!(rgba, [30, 20, 10, 255].repeat(16));
+
+        for format in [RGB565, PAL8] {
+            let info = VideoInfo::new(8, 8, false, FrameType::I, Arc::new(*format));
+            let frame = Frame::new_default_frame(info, None);
+            assert_eq!(frame.to_rgba_thumbnail(4, 4), Err(InvalidConversion));
+        }
+    }
+
+    #[test]
+    fn test_audio_info_simple() {
+        let info = AudioInfo::simple(1024, 48000, ChannelLayout::FivePointOne, formats::S16);
+
+        assert_eq!(info.get_channels_number(), 6);
+        assert_eq!(info.map.layout(), Some(ChannelLayout::FivePointOne));
+        assert_eq!(info.get_sample_rate(), 48000);
+        assert_eq!(info.get_samples(), 1024);
+        assert_eq!(info.get_format(), formats::S16);
+        assert_eq!(info.get_block_len(), None);
+    }
+
+    #[test]
+    fn test_valid_samples() {
+        let info = AudioInfo::new(
+            1024,
+            48000,
+            ChannelMap::default_map(2),
+            Arc::new(formats::S16),
+            None,
+        );
+        let mut frame = Frame::new_default_frame(info, None);
+        let size = frame.buf.as_slice_inner(0).unwrap().len();
+
+        frame.set_valid_samples(700);
+        let audio = frame.kind.get_audio_info().unwrap();
+        assert_eq!(audio.get_samples(), 700);
+        assert_eq!(frame.sample_capacity(), Some(1024));
+        assert_eq!(frame.buf.as_slice_inner(0).unwrap().len(), size);
+        assert_eq!(frame.plane_to_vec(0).unwrap().len(), 700 * 2 * 2);
+
+        frame.set_valid_samples(4096);
+        assert_eq!(frame.kind.get_audio_info().unwrap().get_samples(), 1024);
+    }
+
+    #[test]
+    fn test_deep_clone() {
+        let mut frame = yuv420_frame(42, 42, 16);
+        frame.t.pts = Some(42);
+
+        let mut copy = frame.deep_clone();
+        assert_eq!(copy.t.pts, Some(42));
+        assert_eq!(frame.sad(&copy, 0), Some(0));
+
+        for i in 0..copy.buf.count() {
+            copy.buf.as_mut_slice_inner(i).unwrap().fill(200);
+        }
+        assert_eq!(frame.plane_to_vec(0).unwrap(), vec![16; 42 * 42]);
+        assert_eq!(frame.plane_to_vec(1).unwrap(), vec![16; 21 * 21]);
+        assert_eq!(copy.plane_to_vec(2).unwrap(), vec![200; 21 * 21]);
+    }
+
+    #[test]
+    fn test_swap_bytes_16() {
+        let mut frame = yuv420_frame(16, 16, 0);
+        for i in 0..frame.buf.count() {
+            let data: &mut [u16] = frame.buf.as_mut_slice(i).unwrap();
+            for (j, v) in data.iter_mut().enumerate() {
+                *v = (j as u16).wrapping_mul(0x0102);
+            }
+        }
+        let original: Vec<Vec<u16>> = (0..frame.buf.count())
+            .map(|i| frame.buf.as_slice(i).unwrap().to_vec())
+            .collect();
+
+        frame.swap_bytes_16();
+        for (i, data) in original.iter().enumerate() {
+            let swapped: &[u16] = frame.buf.as_slice(i).unwrap();
+            assert!(swapped.iter().zip(data).all(|(s, d)| *s == d.swap_bytes()));
+        }
+
+        frame.swap_bytes_16();
+        for (i, data) in original.iter().enumerate() {
+            let restored: &[u16] = frame.buf.as_slice(i).unwrap();
+            assert_eq!(restored, &data[..]);
+        }
+    }
+
+    #[test]
+    fn test_plane_alignment() {
+        let frame = yuv420_frame(42, 42, 0);
+
+        for idx in 0..frame.buf.count() {
+            let align = frame.plane_alignment(idx).unwrap();
+            let addr = frame.buf.as_slice_inner(idx).unwrap().as_ptr() as usize;
+            assert!(align.is_power_of_two());
+            assert!(align >= 16);
+            assert_eq!(addr % align, 0);
+            assert_ne!(addr % (align * 2), 0);
+        }
+        assert_eq!(frame.plane_alignment(3), Err(FrameError::InvalidIndex));
+    }
+
+    struct StridedBuffer {
+        planes: Vec<(Vec<u8>, usize)>,
+    }
+
+    impl FrameBuffer for StridedBuffer {
+        fn linesize(&self, idx: usize) -> Result<usize, FrameError> {
+            self.planes.get(idx).map(|p| p.1).ok_or(InvalidIndex)
+        }
+        fn count(&self) -> usize {
+            self.planes.len()
+        }
+        fn as_slice_inner(&self, idx: usize) -> Result<&[u8], FrameError> {
+            self.planes
+                .get(idx)
+                .map(|p| p.0.as_slice())
+                .ok_or(InvalidIndex)
+        }
+        fn as_mut_slice_inner(&mut self, idx: usize) -> Result<&mut [u8], FrameError> {
+            self.planes
+                .get_mut(idx)
+                .map(|p| p.0.as_mut_slice())
+                .ok_or(InvalidIndex)
+        }
+    }
+
+    #[test]
+    fn test_plane_crc32() {
+        let mut frame = yuv420_frame(20, 6, 0);
+        for (idx, v) in [16, 128, 200].into_iter().enumerate() {
+            frame.buf.as_mut_slice_inner(idx).unwrap().fill(v);
+        }
+        frame.buf.as_mut_slice_inner(0).unwrap()[7] = 42;
+
+        // Same content with a wider stride and non-zero padding
+        let planes = (0..frame.buf.count())
+            .map(|idx| {
+                let data = frame.plane_to_vec(idx).unwrap();
+                let width = if idx == 0 { 20 } else { 10 };
+                let linesize = width + 13;
+                let mut buf = vec![0xaa; linesize * data.len() / width];
+                for (dst, src) in buf.chunks_mut(linesize).zip(data.chunks(width)) {
+                    dst[..width].copy_from_slice(src);
+                }
+                (buf, linesize)
+            })
+            .collect();
+        let strided = Frame {
+            kind: frame.kind.clone(),
+            buf: Box::new(StridedBuffer { planes }),
+            t: TimeInfo::default(),
+        };
+
+        for idx in 0..3 {
+            assert_eq!(frame.plane_crc32(idx), strided.plane_crc32(idx));
+        }
+        assert_ne!(frame.plane_crc32(0), frame.plane_crc32(1));
+        assert_eq!(frame.plane_crc32(3), Err(FrameError::InvalidIndex));
+
+        let mut changed = frame.deep_clone();
+        changed.buf.as_mut_slice_inner(2).unwrap()[0] = 0;
+        assert_ne!(frame.plane_crc32(2), changed.plane_crc32(2));
+    }
+
+    #[test]
+    fn test_aliases() {
+        let frame: ArcFrame = Arc::new(yuv420_frame(16, 16, 0));
+        let shared = frame.clone();
+        assert!(frame.aliases(&shared));
+
+        let copy = frame.deep_clone();
+        assert!(!frame.aliases(&copy));
+        assert!(!copy.aliases(&yuv420_frame(16, 16, 0)));
+    }
+
+    #[test]
+    fn test_try_unwrap_frame() {
+        let frame = Arc::new(yuv420_frame(16, 16, 0));
+        assert!(try_unwrap_frame(frame).is_ok());
+
+        let frame = Arc::new(yuv420_frame(16, 16, 0));
+        let other = frame.clone();
+        let frame = try_unwrap_frame(frame).unwrap_err();
+        assert!(Arc::ptr_eq(&frame, &other));
+    }
+
+    #[test]
+    fn test_make_writable() {
+        let mut frame = Arc::new(yuv420_frame(16, 16, 0));
+        assert!(is_writable(&frame));
+        let ptr = Arc::as_ptr(&frame);
+        make_writable(&mut frame).buf.as_mut_slice_inner(0).unwrap()[0] = 1;
+        assert_eq!(Arc::as_ptr(&frame), ptr);
+
+        let shared = frame.clone();
+        assert!(!is_writable(&frame));
+        make_writable(&mut frame).buf.as_mut_slice_inner(0).unwrap()[0] = 2;
+        assert!(!frame.aliases(&shared));
+        assert!(is_writable(&frame));
+        assert_eq!(frame.buf.as_slice_inner(0).unwrap()[0], 2);
+        assert_eq!(shared.buf.as_slice_inner(0).unwrap()[0], 1);
+    }
+
+    #[test]
+    fn test_presentation_order() {
+        let frames = [(0, 0), (1, 2), (2, 3), (3, 1)]
+            .iter()
+            .map(|&(dts, pts)| {
+                let mut frame = yuv420_frame(16, 16, 0);
+                frame.t.dts = Some(dts);
+                frame.t.pts = Some(pts);
+                Arc::new(frame)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(presentation_order(&frames), vec![0, 3, 1, 2]);
+        assert!(presentation_order(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_data_frame() {
+        let text = b"subtitle line";
+        let mut frame = Frame::new_default_frame(MediaKind::Data(text.len()), None);
+
+        assert_eq!(frame.buf.count(), 1);
+        assert_eq!(frame.buf.linesize(0), Ok(text.len()));
+
+        frame.copy_from_slice(std::iter::once(&text[..]), std::iter::once(text.len()));
+        assert_eq!(frame.plane_to_vec(0).unwrap(), text);
+
+        let mut out = [0u8; 8];
+        frame.copy_plane_to_buffer(0, &mut out, 8);
+        assert_eq!(&out, b"subtitle");
+    }
+
+    #[test]
+    fn test_kind_name() {
+        let frame = yuv420_frame(4, 4, 0);
+        assert_eq!(frame.kind.kind_name(), "video");
+        assert_eq!(MediaKind::Data(0).kind_name(), "data");
+    }
+
+    #[test]
+    fn test_rescale_timestamps() {
+        let mut frame = yuv420_frame(4, 4, 0);
+        frame.t.pts = Some(40);
+        frame.t.duration = Some(40);
+        frame.t.timebase = Some(Rational64::new(1, 1000));
+
+        frame.rescale_timestamps(Rational64::new(1, 90000));
+        assert_eq!(frame.t.pts, Some(3600));
+        assert_eq!(frame.t.duration, Some(3600));
+        assert_eq!(frame.t.timebase, Some(Rational64::new(1, 90000)));
+    }
+
+    #[test]
+    fn test_dimension_limits() {
+        let limits = (16384, 16384, 8192 * 8192);
+        let new = |width, height| {
+            VideoInfo::new_checked(
+                width,
+                height,
+                false,
+                FrameType::I,
+                Arc::new(*YUV420),
+                limits.0,
+                limits.1,
+                limits.2,
+            )
+        };
+
+        assert!(new(1920, 1080).is_ok());
+        assert!(matches!(
+            new(100000, 100000),
+            Err(FrameError::InvalidDimensions)
+        ));
+        assert!(matches!(
+            new(16384, 16384),
+            Err(FrameError::InvalidDimensions)
+        ));
+
+        let info = new(16384, 16).unwrap();
+        assert!(info.within_limits(16384, 16, 16384 * 16));
+        assert!(!info.within_limits(16383, 16, usize::MAX));
+        assert!(
+            !VideoInfo::new(usize::MAX, 2, false, FrameType::I, Arc::new(*YUV420)).within_limits(
+                usize::MAX,
+                usize::MAX,
+                usize::MAX
+            )
+        );
+    }
+
+    #[test]
+    fn test_downmix_to_stereo() {
+        use std::f32::consts::FRAC_1_SQRT_2;
+
+        for format in [formats::F32P, formats::F32] {
+            let map = ChannelMap::from_layout(ChannelLayout::FivePointOne);
+            let info = AudioInfo::new(64, 48000, map.clone(), Arc::new(format), None);
+            let mut frame = Frame::new_default_frame(info, None);
+
+            // Center and LFE tones, the LFE must not leak into the stereo output.
+            let mut set = |ch: usize, value: f32| {
+                for i in 0..64 {
+                    let (plane, pos) = if format.planar {
+                        (ch, i)
+                    } else {
+                        (0, i * 6 + ch)
+                    };
+                    let data: &mut [f32] = frame.buf.as_mut_slice(plane).unwrap();
+                    data[pos] = value;
+                }
+            };
+            set(2, 0.5);
+            set(3, 0.9);
+            set(0, 0.9);
+
+            let stereo = frame.downmix_to_stereo().unwrap();
+            let audio = stereo.kind.get_audio_info().unwrap();
+            assert_eq!(audio.map.layout(), Some(ChannelLayout::Stereo));
+            assert_eq!(audio.get_samples(), 64);
+
+            let (left, right) = if format.planar {
+                let l: &[f32] = stereo.buf.as_slice(0).unwrap();
+                let r: &[f32] = stereo.buf.as_slice(1).unwrap();
+                (l[10], r[10])
+            } else {
+                let data: &[f32] = stereo.buf.as_slice(0).unwrap();
+                (data[20], data[21])
+            };
+            assert_eq!(left, 1.0);
+            assert!((right - 0.5 * FRAC_1_SQRT_2).abs() < 1e-6);
+        }
+
+        assert!(matches!(
+            yuv420_frame(4, 4, 0).downmix_to_stereo(),
+            Err(FrameError::InvalidConversion)
+        ));
+    }
+
+    #[test]
+    fn test_bit_depth() {
+        use crate::pixel::formats::YUV420_10;
+
+        assert_eq!(yuv420_frame(4, 4, 0).bit_depth(), 8);
+
+        let info = VideoInfo::new(4, 4, false, FrameType::I, Arc::new(*YUV420_10));
+        assert_eq!(Frame::new_default_frame(info, None).bit_depth(), 10);
+
+        let info = AudioInfo::new(
+            1024,
+            48000,
+            ChannelMap::default_map(2),
+            Arc::new(formats::S16),
+            None,
+        );
+        assert_eq!(Frame::new_default_frame(info, None).bit_depth(), 16);
+    }
 }
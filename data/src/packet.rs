@@ -23,6 +23,8 @@ pub struct Packet {
     pub is_key: bool,
     /// Tells whether a packet is corrupted.
     pub is_corrupted: bool,
+    /// Tells whether a packet signals the end of the stream.
+    pub is_eos: bool,
 }
 
 impl Packet {
@@ -35,6 +37,7 @@ impl Packet {
             stream_index: -1,
             is_key: false,
             is_corrupted: false,
+            is_eos: false,
         }
     }
 
@@ -47,6 +50,7 @@ impl Packet {
             stream_index: -1,
             is_key: false,
             is_corrupted: false,
+            is_eos: false,
         }
     }
 
@@ -54,6 +58,66 @@ impl Packet {
     pub fn new() -> Self {
         Self::with_capacity(0)
     }
+
+    /// Creates an empty `Packet` signaling the end of the stream,
+    /// so that decoders start draining.
+    pub fn eos(stream_index: isize) -> Self {
+        Packet {
+            stream_index,
+            is_eos: true,
+            ..Self::new()
+        }
+    }
+}
+
+/// Packet borrowing its compressed data, e.g. from a demuxer buffer.
+///
+/// Demuxers still return owned packets: their events cannot borrow
+/// the `Context` buffer, since it is moved right after each event.
+#[derive(Debug, Clone)]
+pub struct PacketRef<'a> {
+    /// Packet data.
+    pub data: &'a [u8],
+    /// Packet position in the stream.
+    ///
+    /// If `None`, the packet is not associated to a stream.
+    pub pos: Option<usize>,
+    /// Type of stream the packet is associated to.
+    pub stream_index: isize,
+    /// Packet timestamp information.
+    pub t: TimeInfo,
+
+    /// Tells whether a packet contains a keyframe.
+    pub is_key: bool,
+    /// Tells whether a packet is corrupted.
+    pub is_corrupted: bool,
+}
+
+impl<'a> PacketRef<'a> {
+    /// Creates a new `PacketRef` over the given data.
+    pub fn new(data: &'a [u8]) -> Self {
+        PacketRef {
+            data,
+            t: TimeInfo::default(),
+            pos: None,
+            stream_index: -1,
+            is_key: false,
+            is_corrupted: false,
+        }
+    }
+
+    /// Copies the data into a new owned `Packet`.
+    pub fn to_packet(&self) -> Packet {
+        Packet {
+            data: self.data.to_vec(),
+            pos: self.pos,
+            stream_index: self.stream_index,
+            t: self.t.clone(),
+            is_key: self.is_key,
+            is_corrupted: self.is_corrupted,
+            is_eos: false,
+        }
+    }
 }
 
 /// Used to read a packet from a source.
@@ -98,6 +162,32 @@ mod test {
         }
     }
 
+    #[test]
+    fn packet_ref_to_packet() {
+        let buf: Vec<u8> = (0..128).collect();
+        let mut pkt_ref = PacketRef::new(&buf[16..48]);
+        pkt_ref.stream_index = 1;
+        pkt_ref.pos = Some(16);
+        pkt_ref.is_key = true;
+        pkt_ref.t.pts = Some(42);
+
+        let pkt = pkt_ref.to_packet();
+        assert_eq!(pkt.data, pkt_ref.data);
+        assert_eq!(pkt.stream_index, 1);
+        assert_eq!(pkt.pos, Some(16));
+        assert!(pkt.is_key);
+        assert_eq!(pkt.t.pts, Some(42));
+    }
+
+    #[test]
+    fn eos() {
+        let pkt = Packet::eos(2);
+        assert!(pkt.data.is_empty());
+        assert!(pkt.is_eos);
+        assert_eq!(pkt.stream_index, 2);
+        assert!(!Packet::new().is_eos);
+    }
+
     /*#[test]
     fn test_new(){
         let pkt = Packet::new();
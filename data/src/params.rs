@@ -1,9 +1,35 @@
 //! Video and audio definitions.
 
-use crate::audiosample::{ChannelMap, Soniton};
-use crate::pixel::Formaton;
+use crate::audiosample::{ChannelMap, ChannelType, Soniton};
+use crate::pixel::*;
+use crate::rational::Rational64;
+use std::fmt;
 use std::sync::Arc;
 
+use av_bitstream::byteread::*;
+use av_bitstream::bytewrite::*;
+use num_traits::{FromPrimitive, ToPrimitive};
+
+/// Codec parameters serialization errors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ParamsError {
+    /// The serialized data is truncated or malformed.
+    InvalidData,
+    /// The serialized data has an unknown version.
+    UnsupportedVersion(u8),
+}
+
+impl std::error::Error for ParamsError {}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamsError::InvalidData => write!(f, "Invalid Data"),
+            ParamsError::UnsupportedVersion(v) => write!(f, "Unsupported Version {v}"),
+        }
+    }
+}
+
 /// Video stream information.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct VideoInfo {
@@ -13,6 +39,39 @@ pub struct VideoInfo {
     pub height: usize,
     /// Picture pixel format.
     pub format: Option<Arc<Formaton>>,
+    /// Picture frame rate.
+    ///
+    /// If `None`, the frame rate is unknown or variable.
+    pub frame_rate: Option<Rational64>,
+}
+
+impl VideoInfo {
+    /// Sets the frame rate of the video stream.
+    pub fn with_frame_rate(mut self, frame_rate: Rational64) -> Self {
+        self.frame_rate = Some(frame_rate);
+        self
+    }
+
+    /// Returns the frame rate of the video stream.
+    pub fn get_frame_rate(&self) -> Option<Rational64> {
+        self.frame_rate
+    }
+
+    /// Returns the number of whole frames in the interval between
+    /// `ts_start` and `ts_end`, expressed in `timebase` units.
+    ///
+    /// If the frame rate is unknown, `None` is returned.
+    pub fn frames_between(&self, ts_start: i64, ts_end: i64, timebase: Rational64) -> Option<u64> {
+        let rate = self.frame_rate?;
+        let num =
+            (ts_end as i128 - ts_start as i128) * *timebase.numer() as i128 * *rate.numer() as i128;
+        let den = *timebase.denom() as i128 * *rate.denom() as i128;
+        if den == 0 {
+            return None;
+        }
+
+        Some((num / den).clamp(0, u64::MAX as i128) as u64)
+    }
 }
 
 /// Audio stream information.
@@ -35,6 +94,16 @@ pub enum MediaKind {
     Audio(AudioInfo),
 }
 
+impl MediaKind {
+    /// Returns a short name describing the kind of stream.
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            MediaKind::Video(_) => "video",
+            MediaKind::Audio(_) => "audio",
+        }
+    }
+}
+
 /// Possible codec parameters.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CodecParams {
@@ -53,3 +122,520 @@ pub struct CodecParams {
     /// before returning data.
     pub delay: usize,
 }
+
+impl CodecParams {
+    /// Returns a short name describing the kind of stream, if known.
+    pub fn media_type(&self) -> Option<&'static str> {
+        self.kind.as_ref().map(MediaKind::kind_name)
+    }
+
+    /// Serializes the parameters into a versioned, big-endian byte blob.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![PARAMS_VERSION];
+
+        match self.kind {
+            None => buf.push(0),
+            Some(MediaKind::Video(ref video)) => {
+                buf.push(1);
+                push_u64(&mut buf, video.width as u64);
+                push_u64(&mut buf, video.height as u64);
+                buf.push(video.format.is_some() as u8);
+                if let Some(ref fmt) = video.format {
+                    push_formaton(&mut buf, fmt);
+                }
+                buf.push(video.frame_rate.is_some() as u8);
+                if let Some(rate) = video.frame_rate {
+                    push_u64(&mut buf, *rate.numer() as u64);
+                    push_u64(&mut buf, *rate.denom() as u64);
+                }
+            }
+            Some(MediaKind::Audio(ref audio)) => {
+                buf.push(2);
+                push_u64(&mut buf, audio.rate as u64);
+                buf.push(audio.map.is_some() as u8);
+                if let Some(ref map) = audio.map {
+                    push_u64(&mut buf, map.len() as u64);
+                    for idx in 0..map.len() {
+                        buf.push(map.get_channel(idx).to_u8().unwrap());
+                    }
+                }
+                buf.push(audio.format.is_some() as u8);
+                if let Some(ref fmt) = audio.format {
+                    buf.extend_from_slice(&[
+                        fmt.bits,
+                        fmt.be as u8,
+                        fmt.packed as u8,
+                        fmt.planar as u8,
+                        fmt.float as u8,
+                        fmt.signed as u8,
+                    ]);
+                }
+            }
+        }
+
+        push_opt_bytes(&mut buf, self.codec_id.as_ref().map(|id| id.as_bytes()));
+        push_opt_bytes(&mut buf, self.extradata.as_deref());
+        push_u64(&mut buf, self.bit_rate as u64);
+        push_u64(&mut buf, self.convergence_window as u64);
+        push_u64(&mut buf, self.delay as u64);
+
+        buf
+    }
+
+    /// Deserializes parameters produced by `to_bytes`.
+    pub fn from_bytes(buf: &[u8]) -> Result<CodecParams, ParamsError> {
+        let mut r = ParamsReader { buf };
+
+        let version = r.u8()?;
+        if version != PARAMS_VERSION {
+            return Err(ParamsError::UnsupportedVersion(version));
+        }
+
+        let kind = match r.u8()? {
+            0 => None,
+            1 => {
+                let width = r.usize()?;
+                let height = r.usize()?;
+                let format = if r.flag()? {
+                    Some(Arc::new(r.formaton()?))
+                } else {
+                    None
+                };
+                let frame_rate = if r.flag()? {
+                    let num = r.u64()? as i64;
+                    let den = r.u64()? as i64;
+                    if den == 0 {
+                        return Err(ParamsError::InvalidData);
+                    }
+                    Some(Rational64::new(num, den))
+                } else {
+                    None
+                };
+
+                Some(MediaKind::Video(VideoInfo {
+                    width,
+                    height,
+                    format,
+                    frame_rate,
+                }))
+            }
+            2 => {
+                let rate = r.usize()?;
+                let map = if r.flag()? {
+                    let mut map = ChannelMap::new();
+                    for _ in 0..r.usize()? {
+                        let ch = ChannelType::from_u8(r.u8()?).ok_or(ParamsError::InvalidData)?;
+                        map.add_channel(ch);
+                    }
+                    Some(map)
+                } else {
+                    None
+                };
+                let format = if r.flag()? {
+                    let bits = r.u8()?;
+                    Some(Arc::new(Soniton::new(
+                        bits,
+                        r.flag()?,
+                        r.flag()?,
+                        r.flag()?,
+                        r.flag()?,
+                        r.flag()?,
+                    )))
+                } else {
+                    None
+                };
+
+                Some(MediaKind::Audio(AudioInfo { rate, map, format }))
+            }
+            _ => return Err(ParamsError::InvalidData),
+        };
+
+        let codec_id = match r.opt_bytes()? {
+            Some(id) => Some(String::from_utf8(id).map_err(|_| ParamsError::InvalidData)?),
+            None => None,
+        };
+        let extradata = r.opt_bytes()?;
+        let bit_rate = r.usize()?;
+        let convergence_window = r.usize()?;
+        let delay = r.usize()?;
+
+        if !r.buf.is_empty() {
+            return Err(ParamsError::InvalidData);
+        }
+
+        Ok(CodecParams {
+            kind,
+            codec_id,
+            extradata,
+            bit_rate,
+            convergence_window,
+            delay,
+        })
+    }
+}
+
+const PARAMS_VERSION: u8 = 1;
+
+fn push_u64(buf: &mut Vec<u8>, n: u64) {
+    let mut bytes = [0; 8];
+    put_u64b(&mut bytes, n);
+    buf.extend_from_slice(&bytes);
+}
+
+fn color_model_code(model: ColorModel) -> u8 {
+    use self::TrichromaticEncodingSystem::*;
+    use self::YUVSystem::*;
+
+    match model {
+        ColorModel::Trichromatic(RGB) => 0,
+        ColorModel::Trichromatic(YUV(YCbCr(YUVRange::Limited))) => 1,
+        ColorModel::Trichromatic(YUV(YCbCr(YUVRange::Full))) => 2,
+        ColorModel::Trichromatic(YUV(YCoCg)) => 3,
+        ColorModel::Trichromatic(YUV(ICtCp)) => 4,
+        ColorModel::Trichromatic(XYZ) => 5,
+        ColorModel::CMYK => 6,
+        ColorModel::HSV => 7,
+        ColorModel::LAB => 8,
+    }
+}
+
+fn color_model_from_code(code: u8) -> Option<ColorModel> {
+    use self::TrichromaticEncodingSystem::*;
+    use self::YUVSystem::*;
+
+    let model = match code {
+        0 => ColorModel::Trichromatic(RGB),
+        1 => ColorModel::Trichromatic(YUV(YCbCr(YUVRange::Limited))),
+        2 => ColorModel::Trichromatic(YUV(YCbCr(YUVRange::Full))),
+        3 => ColorModel::Trichromatic(YUV(YCoCg)),
+        4 => ColorModel::Trichromatic(YUV(ICtCp)),
+        5 => ColorModel::Trichromatic(XYZ),
+        6 => ColorModel::CMYK,
+        7 => ColorModel::HSV,
+        8 => ColorModel::LAB,
+        _ => return None,
+    };
+
+    Some(model)
+}
+
+fn push_formaton(buf: &mut Vec<u8>, fmt: &Formaton) {
+    buf.extend_from_slice(&[
+        color_model_code(fmt.model),
+        fmt.primaries.to_u8().unwrap(),
+        fmt.xfer.to_u8().unwrap(),
+        fmt.matrix.to_u8().unwrap(),
+        fmt.chroma_location.to_u8().unwrap(),
+        fmt.components,
+    ]);
+    for chromaton in fmt.comp_info.iter() {
+        buf.push(chromaton.is_some() as u8);
+        if let Some(c) = chromaton {
+            buf.extend_from_slice(&[
+                c.h_ss,
+                c.v_ss,
+                c.packed as u8,
+                c.depth,
+                c.shift,
+                c.comp_offs,
+                c.next_elem,
+            ]);
+        }
+    }
+    buf.extend_from_slice(&[
+        fmt.elem_size,
+        fmt.be as u8,
+        fmt.alpha as u8,
+        fmt.palette as u8,
+    ]);
+}
+
+fn push_opt_bytes(buf: &mut Vec<u8>, data: Option<&[u8]>) {
+    buf.push(data.is_some() as u8);
+    if let Some(data) = data {
+        push_u64(buf, data.len() as u64);
+        buf.extend_from_slice(data);
+    }
+}
+
+struct ParamsReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> ParamsReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ParamsError> {
+        if self.buf.len() < n {
+            return Err(ParamsError::InvalidData);
+        }
+        let (data, rest) = self.buf.split_at(n);
+        self.buf = rest;
+
+        Ok(data)
+    }
+
+    fn u8(&mut self) -> Result<u8, ParamsError> {
+        Ok(get_u8(self.take(1)?))
+    }
+
+    fn flag(&mut self) -> Result<bool, ParamsError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(ParamsError::InvalidData),
+        }
+    }
+
+    fn u64(&mut self) -> Result<u64, ParamsError> {
+        Ok(get_u64b(self.take(8)?))
+    }
+
+    fn usize(&mut self) -> Result<usize, ParamsError> {
+        usize::try_from(self.u64()?).map_err(|_| ParamsError::InvalidData)
+    }
+
+    fn formaton(&mut self) -> Result<Formaton, ParamsError> {
+        fn from_code<T: FromPrimitive>(code: u8) -> Result<T, ParamsError> {
+            T::from_u8(code).ok_or(ParamsError::InvalidData)
+        }
+
+        let model = color_model_from_code(self.u8()?).ok_or(ParamsError::InvalidData)?;
+        let primaries = from_code(self.u8()?)?;
+        let xfer = from_code(self.u8()?)?;
+        let matrix = from_code(self.u8()?)?;
+        let chroma_location = from_code(self.u8()?)?;
+        let components = self.u8()?;
+        let mut comp_info = [None; 5];
+        for chromaton in comp_info.iter_mut() {
+            if self.flag()? {
+                let c = Chromaton {
+                    h_ss: self.u8()?,
+                    v_ss: self.u8()?,
+                    packed: self.flag()?,
+                    depth: self.u8()?,
+                    shift: self.u8()?,
+                    comp_offs: self.u8()?,
+                    next_elem: self.u8()?,
+                };
+                // Subsampling factors are used as shift amounts.
+                if c.h_ss > 7 || c.v_ss > 7 || c.depth == 0 || c.depth > 32 {
+                    return Err(ParamsError::InvalidData);
+                }
+                *chromaton = Some(c);
+            }
+        }
+        if usize::from(components) != comp_info.iter().flatten().count() {
+            return Err(ParamsError::InvalidData);
+        }
+
+        Ok(Formaton {
+            model,
+            primaries,
+            xfer,
+            matrix,
+            chroma_location,
+            components,
+            comp_info,
+            elem_size: self.u8()?,
+            be: self.flag()?,
+            alpha: self.flag()?,
+            palette: self.flag()?,
+        })
+    }
+
+    fn opt_bytes(&mut self) -> Result<Option<Vec<u8>>, ParamsError> {
+        if !self.flag()? {
+            return Ok(None);
+        }
+        let len = self.usize()?;
+
+        Ok(Some(self.take(len)?.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frame_rate() {
+        let info = VideoInfo {
+            width: 1920,
+            height: 1080,
+            format: None,
+            frame_rate: None,
+        }
+        .with_frame_rate(Rational64::new(30000, 1001));
+
+        let params = CodecParams {
+            kind: Some(MediaKind::Video(info)),
+            codec_id: None,
+            extradata: None,
+            bit_rate: 0,
+            convergence_window: 0,
+            delay: 0,
+        };
+
+        match params.kind {
+            Some(MediaKind::Video(info)) => {
+                assert_eq!(info.get_frame_rate(), Some(Rational64::new(30000, 1001)))
+            }
+            _ => panic!("Invalid media kind"),
+        }
+    }
+
+    #[test]
+    fn frames_between() {
+        let mut info = VideoInfo {
+            width: 1920,
+            height: 1080,
+            format: None,
+            frame_rate: None,
+        };
+        let timebase = Rational64::new(1, 90000);
+        assert_eq!(info.frames_between(0, 90000, timebase), None);
+
+        info = info.with_frame_rate(Rational64::from_integer(25));
+        assert_eq!(info.frames_between(0, 90000, timebase), Some(25));
+        assert_eq!(info.frames_between(90000, 135000, timebase), Some(12));
+        assert_eq!(info.frames_between(90000, 0, timebase), Some(0));
+
+        info = info.with_frame_rate(Rational64::new(30000, 1001));
+        assert_eq!(
+            info.frames_between(0, 1001, Rational64::new(1, 1000)),
+            Some(30)
+        );
+    }
+
+    #[test]
+    fn media_type() {
+        let video = VideoInfo {
+            width: 640,
+            height: 480,
+            format: None,
+            frame_rate: None,
+        };
+        let audio = AudioInfo {
+            rate: 48000,
+            map: None,
+            format: None,
+        };
+        let mut params = CodecParams {
+            kind: None,
+            codec_id: None,
+            extradata: None,
+            bit_rate: 0,
+            convergence_window: 0,
+            delay: 0,
+        };
+
+        assert_eq!(params.media_type(), None);
+        params.kind = Some(MediaKind::Video(video));
+        assert_eq!(params.media_type(), Some("video"));
+        params.kind = Some(MediaKind::Audio(audio));
+        assert_eq!(params.media_type(), Some("audio"));
+    }
+
+    #[test]
+    fn serialize() {
+        use crate::audiosample::formats::S16;
+        use crate::audiosample::ChannelLayout;
+        use crate::pixel::formats::YUV420_10;
+
+        let video = VideoInfo {
+            width: 1920,
+            height: 1080,
+            format: Some(Arc::new(*YUV420_10)),
+            frame_rate: Some(Rational64::new(30000, 1001)),
+        };
+        let mut params = CodecParams {
+            kind: Some(MediaKind::Video(video)),
+            codec_id: Some("av1".to_owned()),
+            extradata: Some(vec![0x81, 0x00, 0x0c, 0x00]),
+            bit_rate: 4_000_000,
+            convergence_window: 0,
+            delay: 2,
+        };
+
+        let buf = params.to_bytes();
+        assert_eq!(CodecParams::from_bytes(&buf), Ok(params.clone()));
+        assert_eq!(
+            CodecParams::from_bytes(&buf[..buf.len() - 1]),
+            Err(ParamsError::InvalidData)
+        );
+        let mut bad = buf.clone();
+        bad[0] = 0xff;
+        assert_eq!(
+            CodecParams::from_bytes(&bad),
+            Err(ParamsError::UnsupportedVersion(0xff))
+        );
+
+        params.kind = Some(MediaKind::Audio(AudioInfo {
+            rate: 48000,
+            map: Some(ChannelMap::from_layout(ChannelLayout::FivePointOne)),
+            format: Some(Arc::new(S16)),
+        }));
+        params.extradata = None;
+        let buf = params.to_bytes();
+        assert_eq!(CodecParams::from_bytes(&buf), Ok(params.clone()));
+
+        let mut fmt = *YUV420_10;
+        fmt.model = ColorModel::Trichromatic(TrichromaticEncodingSystem::YUV(YUVSystem::YCbCr(
+            YUVRange::Full,
+        )));
+        fmt.primaries = ColorPrimaries::BT2020;
+        fmt.chroma_location = ChromaLocation::TopLeft;
+        assert!(!formats::all().contains(&&fmt));
+        params.kind = Some(MediaKind::Video(VideoInfo {
+            width: 3840,
+            height: 2160,
+            format: Some(Arc::new(fmt)),
+            frame_rate: None,
+        }));
+        let buf = params.to_bytes();
+        assert_eq!(CodecParams::from_bytes(&buf), Ok(params));
+    }
+
+    #[test]
+    fn deserialize_malformed_formaton() {
+        use crate::pixel::formats::YUV420;
+
+        let from_format = |fmt: Formaton| {
+            let params = CodecParams {
+                kind: Some(MediaKind::Video(VideoInfo {
+                    width: 64,
+                    height: 64,
+                    format: Some(Arc::new(fmt)),
+                    frame_rate: None,
+                })),
+                codec_id: None,
+                extradata: None,
+                bit_rate: 0,
+                convergence_window: 0,
+                delay: 0,
+            };
+            CodecParams::from_bytes(&params.to_bytes())
+        };
+
+        let mut fmt = *YUV420;
+        fmt.components = 6;
+        assert_eq!(from_format(fmt), Err(ParamsError::InvalidData));
+
+        let mut fmt = *YUV420;
+        fmt.components = 2;
+        assert_eq!(from_format(fmt), Err(ParamsError::InvalidData));
+
+        let mut fmt = *YUV420;
+        fmt.comp_info[3] = fmt.comp_info[0];
+        assert_eq!(from_format(fmt), Err(ParamsError::InvalidData));
+
+        let mut fmt = *YUV420;
+        fmt.comp_info[1].as_mut().unwrap().depth = 0;
+        assert_eq!(from_format(fmt), Err(ParamsError::InvalidData));
+
+        let mut fmt = *YUV420;
+        fmt.comp_info[1].as_mut().unwrap().h_ss = 64;
+        assert_eq!(from_format(fmt), Err(ParamsError::InvalidData));
+
+        assert!(from_format(*YUV420).is_ok());
+    }
+}
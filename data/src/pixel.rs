@@ -328,7 +328,7 @@ impl fmt::Display for TransferCharacteristic {
 /// and half the height of the associated luma array)
 ///
 /// Values adopted from Table 4 of ISO/IEC 23001-8:2013/DCOR1.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 #[allow(missing_docs)]
 pub enum ChromaLocation {
     Unspecified = 0,
@@ -480,6 +480,14 @@ fn align(v: usize, a: usize) -> usize {
     (v + a - 1) & !(a - 1)
 }
 
+fn subsample(v: usize, ss: u8, round_up: bool) -> usize {
+    if round_up {
+        (v + ((1 << ss) - 1)) >> ss
+    } else {
+        v >> ss
+    }
+}
+
 impl Chromaton {
     /// Constructs a new `Chromaton` instance.
     pub const fn new(
@@ -554,18 +562,44 @@ impl Chromaton {
 
     /// Calculates the width for a component from general image width.
     pub fn get_width(self, width: usize) -> usize {
-        (width + ((1 << self.h_ss) - 1)) >> self.h_ss
+        self.get_width_rounded(width, true)
+    }
+
+    /// Calculates the width for a component from general image width,
+    /// rounding the subsampled size either up or down.
+    pub fn get_width_rounded(self, width: usize, round_up: bool) -> usize {
+        subsample(width, self.h_ss, round_up)
     }
 
     /// Calculates the height for a component from general image height.
     pub fn get_height(self, height: usize) -> usize {
-        (height + ((1 << self.v_ss) - 1)) >> self.v_ss
+        self.get_height_rounded(height, true)
+    }
+
+    /// Calculates the height for a component from general image height,
+    /// rounding the subsampled size either up or down.
+    pub fn get_height_rounded(self, height: usize, round_up: bool) -> usize {
+        subsample(height, self.v_ss, round_up)
     }
 
     /// Calculates the minimal stride for a component from general image width.
+    ///
+    /// Samples of non-packed components are stored in whole bytes.
     pub fn get_linesize(self, width: usize, alignment: usize) -> usize {
+        self.get_linesize_rounded(width, alignment, true)
+    }
+
+    /// Calculates the minimal stride for a component from general image width,
+    /// rounding the subsampled width either up or down.
+    pub fn get_linesize_rounded(self, width: usize, alignment: usize, round_up: bool) -> usize {
         let d = self.depth as usize;
-        align((self.get_width(width) * d + d - 1) >> 3, alignment)
+        let width = self.get_width_rounded(width, round_up);
+        let size = if self.packed {
+            (width * d + d - 1) >> 3
+        } else {
+            width * ((d + 7) >> 3)
+        };
+        align(size, alignment)
     }
 
     /// Calculates the required image size in pixels for a component
@@ -591,6 +625,20 @@ impl fmt::Display for Chromaton {
     }
 }
 
+/// Color description of an image, e.g. as signaled by the video
+/// usability information of a bitstream.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct ColorInfo {
+    /// Image color primaries.
+    pub primaries: ColorPrimaries,
+    /// Image transfer characteristic.
+    pub xfer: TransferCharacteristic,
+    /// Image matrix coefficients.
+    pub matrix: MatrixCoefficients,
+    /// Image chroma location.
+    pub chroma_location: ChromaLocation,
+}
+
 /// Image colorspace representation.
 ///
 /// Includes both definitions for each component and some common definitions.
@@ -661,6 +709,55 @@ impl Formaton {
         }
     }
 
+    /// Constructs a planar YUV format of the given depth and chroma
+    /// subsampling, with an optional alpha plane.
+    ///
+    /// The subsampling is expressed as the log2 of the horizontal and
+    /// vertical chroma decimation, so `(1, 1)` is 4:2:0.
+    pub fn planar_yuv(depth: u8, h_ss: u8, v_ss: u8, alpha: bool) -> Self {
+        let mut components = vec![
+            Chromaton::new(0, 0, false, depth, 0, 0, 1),
+            Chromaton::yuvhb(h_ss, v_ss, depth, 1),
+            Chromaton::yuvhb(h_ss, v_ss, depth, 2),
+        ];
+        if alpha {
+            components.push(Chromaton::new(0, 0, false, depth, 0, 3, 1));
+        }
+
+        Formaton::new(
+            ColorModel::Trichromatic(TrichromaticEncodingSystem::YUV(YUVSystem::YCbCr(
+                YUVRange::Limited,
+            ))),
+            &components,
+            0,
+            false,
+            alpha,
+            false,
+        )
+    }
+
+    /// Constructs a packed RGB format with components of the given depth,
+    /// with an optional alpha component.
+    ///
+    /// The components are stored in reverse order, as in the
+    /// predefined `RGB24` and `RGBA` formats.
+    pub fn packed_rgb(depth: u8, alpha: bool, be: bool) -> Self {
+        let count = if alpha { 4 } else { 3 };
+        let elem_size = count * depth.div_ceil(8);
+        let components: Vec<_> = (0..count)
+            .map(|i| Chromaton::packrgb(depth, 0, count - 1 - i, elem_size))
+            .collect();
+
+        Formaton::new(
+            ColorModel::Trichromatic(TrichromaticEncodingSystem::RGB),
+            &components,
+            elem_size,
+            be,
+            alpha,
+            false,
+        )
+    }
+
     /// Returns current color model.
     pub fn get_model(&self) -> ColorModel {
         self.model
@@ -681,12 +778,12 @@ impl Formaton {
     }
 
     /// Sets current image primaries.
-    pub fn set_primaries(mut self, pc: ColorPrimaries) {
+    pub fn set_primaries(&mut self, pc: ColorPrimaries) {
         self.primaries = pc;
     }
 
     /// Sets current image primaries from `u32`.
-    pub fn set_primaries_from_u32(mut self, pc: u32) -> Option<ColorPrimaries> {
+    pub fn set_primaries_from_u32(&mut self, pc: u32) -> Option<ColorPrimaries> {
         let parsed_pc = ColorPrimaries::from_u32(pc);
         if let Some(pc) = parsed_pc {
             self.primaries = pc
@@ -700,12 +797,12 @@ impl Formaton {
     }
 
     /// Sets current image transfer characteristic.
-    pub fn set_xfer(mut self, pc: TransferCharacteristic) {
+    pub fn set_xfer(&mut self, pc: TransferCharacteristic) {
         self.xfer = pc;
     }
 
     /// Sets current image transfer characteristic from `u32`.
-    pub fn set_xfer_from_u32(mut self, tc: u32) -> Option<TransferCharacteristic> {
+    pub fn set_xfer_from_u32(&mut self, tc: u32) -> Option<TransferCharacteristic> {
         let parsed_tc = TransferCharacteristic::from_u32(tc);
         if let Some(tc) = parsed_tc {
             self.xfer = tc
@@ -719,12 +816,12 @@ impl Formaton {
     }
 
     /// Sets current image matrix coefficients.
-    pub fn set_matrix(mut self, mc: MatrixCoefficients) {
+    pub fn set_matrix(&mut self, mc: MatrixCoefficients) {
         self.matrix = mc;
     }
 
     /// Sets current image matrix coefficients from `u32`.
-    pub fn set_matrix_from_u32(mut self, mc: u32) -> Option<MatrixCoefficients> {
+    pub fn set_matrix_from_u32(&mut self, mc: u32) -> Option<MatrixCoefficients> {
         let parsed_mc = MatrixCoefficients::from_u32(mc);
         if let Some(mc) = parsed_mc {
             self.matrix = mc
@@ -732,6 +829,41 @@ impl Formaton {
         parsed_mc
     }
 
+    /// Sets the color description of the image.
+    pub fn with_color_info(
+        mut self,
+        primaries: ColorPrimaries,
+        xfer: TransferCharacteristic,
+        matrix: MatrixCoefficients,
+        chroma_location: ChromaLocation,
+    ) -> Self {
+        self.apply_color_info(&ColorInfo {
+            primaries,
+            xfer,
+            matrix,
+            chroma_location,
+        });
+        self
+    }
+
+    /// Sets all the fields of a color description at once.
+    pub fn apply_color_info(&mut self, info: &ColorInfo) {
+        self.primaries = info.primaries;
+        self.xfer = info.xfer;
+        self.matrix = info.matrix;
+        self.chroma_location = info.chroma_location;
+    }
+
+    /// Returns the chroma location, normalized to `Unspecified` when
+    /// no component is subsampled.
+    pub fn effective_chroma_location(&self) -> ChromaLocation {
+        if self.iter().flatten().any(|c| c.get_subsampling() != (0, 0)) {
+            self.chroma_location
+        } else {
+            ChromaLocation::Unspecified
+        }
+    }
+
     /// Returns the number of components.
     pub fn get_num_comp(&self) -> usize {
         self.components as usize
@@ -765,12 +897,77 @@ impl Formaton {
     }
 
     /// Returns an iterator over the format definition of each component.
-    pub fn iter(&self) -> slice::Iter<Option<Chromaton>> {
+    pub fn iter(&self) -> slice::Iter<'_, Option<Chromaton>> {
         self.comp_info.iter()
     }
+
+    /// Returns a short FFmpeg-style name for the format (e.g. `yuv420p10le`).
+    ///
+    /// Formats without a well-known short name fall back to the verbose
+    /// `Display` representation.
+    pub fn short_name(&self) -> String {
+        let end = if self.be { "be" } else { "le" };
+        let depth = self.get_chromaton(0).map_or(0, |c| c.get_depth());
+        let suffix = if depth > 8 {
+            format!("{}{}", depth, end)
+        } else {
+            String::new()
+        };
+        let planar = self.iter().flatten().all(|c| !c.is_packed());
+
+        if self.palette {
+            return "pal8".to_string();
+        }
+
+        match self.model {
+            ColorModel::Trichromatic(TrichromaticEncodingSystem::YUV(system)) if planar => {
+                let ss = match self.get_chromaton(1).map(|c| c.get_subsampling()) {
+                    Some((0, 0)) => "444",
+                    Some((1, 0)) => "422",
+                    Some((1, 1)) => "420",
+                    Some((0, 1)) => "440",
+                    Some((2, 0)) => "411",
+                    Some((2, 1)) => "410",
+                    _ => return self.to_string(),
+                };
+                let prefix = if self.alpha {
+                    "yuva"
+                } else if system == YUVSystem::YCbCr(YUVRange::Full) && depth == 8 {
+                    "yuvj"
+                } else {
+                    "yuv"
+                };
+                format!("{}{}p{}", prefix, ss, suffix)
+            }
+            ColorModel::Trichromatic(TrichromaticEncodingSystem::RGB) if !planar => {
+                // Only the component layouts of the predefined formats
+                // have a well-known name.
+                let packed = [
+                    (formats::RGB565, "rgb565", true),
+                    (formats::RGB24, "rgb24", false),
+                    (formats::RGBA, "rgba", false),
+                    (formats::RGB48, "rgb48", true),
+                    (formats::RGBA64, "rgba64", true),
+                ];
+                match packed
+                    .iter()
+                    .find(|(f, _, _)| f.comp_info == self.comp_info)
+                {
+                    Some((_, name, true)) => format!("{}{}", name, end),
+                    Some((_, name, false)) => name.to_string(),
+                    None => self.to_string(),
+                }
+            }
+            ColorModel::Trichromatic(TrichromaticEncodingSystem::RGB) => {
+                let prefix = if self.alpha { "gbrap" } else { "gbrp" };
+                format!("{}{}", prefix, suffix)
+            }
+            _ => self.to_string(),
+        }
+    }
 }
 
-impl<'a> Index<usize> for &'a Formaton {
+impl Index<usize> for &Formaton {
     type Output = Option<Chromaton>;
 
     fn index(&self, index: usize) -> &Self::Output {
@@ -847,8 +1044,8 @@ pub mod formats {
         components: 3,
         comp_info: [
             Some(Chromaton::new(0, 0, false, 8, 0, 0, 1)),
-            Some(Chromaton::yuv8(0, 1, 1)),
-            Some(Chromaton::yuv8(0, 1, 2)),
+            Some(Chromaton::yuv8(1, 0, 1)),
+            Some(Chromaton::yuv8(1, 0, 2)),
             None,
             None,
         ],
@@ -931,8 +1128,8 @@ pub mod formats {
         components: 3,
         comp_info: [
             Some(Chromaton::new(0, 0, false, 10, 0, 0, 1)),
-            Some(Chromaton::yuvhb(0, 0, 1, 10)),
-            Some(Chromaton::yuvhb(0, 0, 2, 10)),
+            Some(Chromaton::yuvhb(0, 0, 10, 1)),
+            Some(Chromaton::yuvhb(0, 0, 10, 2)),
             None,
             None,
         ],
@@ -952,8 +1149,8 @@ pub mod formats {
         components: 3,
         comp_info: [
             Some(Chromaton::new(0, 0, false, 10, 0, 0, 1)),
-            Some(Chromaton::yuvhb(0, 1, 1, 10)),
-            Some(Chromaton::yuvhb(0, 1, 2, 10)),
+            Some(Chromaton::yuvhb(1, 0, 10, 1)),
+            Some(Chromaton::yuvhb(1, 0, 10, 2)),
             None,
             None,
         ],
@@ -973,8 +1170,8 @@ pub mod formats {
         components: 3,
         comp_info: [
             Some(Chromaton::new(0, 0, false, 10, 0, 0, 1)),
-            Some(Chromaton::yuvhb(1, 1, 1, 10)),
-            Some(Chromaton::yuvhb(1, 1, 2, 10)),
+            Some(Chromaton::yuvhb(1, 1, 10, 1)),
+            Some(Chromaton::yuvhb(1, 1, 10, 2)),
             None,
             None,
         ],
@@ -994,8 +1191,8 @@ pub mod formats {
         components: 3,
         comp_info: [
             Some(Chromaton::new(0, 0, false, 10, 0, 0, 1)),
-            Some(Chromaton::yuvhb(2, 0, 1, 10)),
-            Some(Chromaton::yuvhb(2, 0, 2, 10)),
+            Some(Chromaton::yuvhb(2, 0, 10, 1)),
+            Some(Chromaton::yuvhb(2, 0, 10, 2)),
             None,
             None,
         ],
@@ -1015,8 +1212,30 @@ pub mod formats {
         components: 3,
         comp_info: [
             Some(Chromaton::new(0, 0, false, 10, 0, 0, 1)),
-            Some(Chromaton::yuvhb(2, 1, 1, 10)),
-            Some(Chromaton::yuvhb(2, 1, 2, 10)),
+            Some(Chromaton::yuvhb(2, 1, 10, 1)),
+            Some(Chromaton::yuvhb(2, 1, 10, 2)),
+            None,
+            None,
+        ],
+        elem_size: 0,
+        be: false,
+        alpha: false,
+        palette: false,
+    };
+
+    /// Predefined format for planar YUV with 10-bit luma, 8-bit chroma
+    /// and 4:2:0 subsampling.
+    pub const YUV420_10_8: &Formaton = &Formaton {
+        model: Trichromatic(YUV(YCbCr(Limited))),
+        primaries: ColorPrimaries::Unspecified,
+        xfer: TransferCharacteristic::Unspecified,
+        matrix: MatrixCoefficients::Unspecified,
+        chroma_location: ChromaLocation::Unspecified,
+        components: 3,
+        comp_info: [
+            Some(Chromaton::new(0, 0, false, 10, 0, 0, 1)),
+            Some(Chromaton::yuv8(1, 1, 1)),
+            Some(Chromaton::yuv8(1, 1, 2)),
             None,
             None,
         ],
@@ -1089,6 +1308,27 @@ pub mod formats {
         palette: false,
     };
 
+    /// Predefined format for planar RGB, stored as G, B and R planes.
+    pub const GBRP: &Formaton = &Formaton {
+        model: Trichromatic(RGB),
+        primaries: ColorPrimaries::Unspecified,
+        xfer: TransferCharacteristic::Unspecified,
+        matrix: MatrixCoefficients::Unspecified,
+        chroma_location: ChromaLocation::Unspecified,
+        components: 3,
+        comp_info: [
+            Some(Chromaton::yuv8(0, 0, 2)),
+            Some(Chromaton::yuv8(0, 0, 0)),
+            Some(Chromaton::yuv8(0, 0, 1)),
+            None,
+            None,
+        ],
+        elem_size: 0,
+        be: false,
+        alpha: false,
+        palette: false,
+    };
+
     /// Predefined format for RGBA.
     pub const RGBA: &Formaton = &Formaton {
         model: Trichromatic(RGB),
@@ -1151,6 +1391,87 @@ pub mod formats {
         alpha: true,
         palette: false,
     };
+
+    /// Returns all the predefined formats.
+    pub fn all() -> &'static [&'static Formaton] {
+        &[
+            YUV444,
+            YUV422,
+            YUV420,
+            YUV411,
+            YUV410,
+            YUV444_10,
+            YUV422_10,
+            YUV420_10,
+            YUV411_10,
+            YUV410_10,
+            YUV420_10_8,
+            PAL8,
+            RGB565,
+            RGB24,
+            GBRP,
+            RGBA,
+            RGB48,
+            RGBA64,
+        ]
+    }
+}
+
+// Returns the byte offset in RGB24 and the plane index in GBRP
+// of each RGB component.
+fn rgb_layout() -> impl Iterator<Item = (usize, usize)> {
+    formats::RGB24
+        .iter()
+        .zip(formats::GBRP.iter())
+        .take(3)
+        .flat_map(|(p, c)| {
+            Some((
+                p.as_ref()?.get_offset() as usize,
+                c.as_ref()?.get_offset() as usize,
+            ))
+        })
+}
+
+/// Splits `RGB24` pixels into the planes of a `GBRP` picture.
+///
+/// The destination planes are given in storage order.
+pub fn rgb24_to_gbrp(
+    src: &[u8],
+    src_stride: usize,
+    dst: [&mut [u8]; 3],
+    dst_strides: [usize; 3],
+    width: usize,
+    height: usize,
+) {
+    for (offset, plane) in rgb_layout() {
+        let lines = dst[plane].chunks_mut(dst_strides[plane]);
+        for (dline, sline) in lines.zip(src.chunks(src_stride)).take(height) {
+            for (d, s) in dline[..width].iter_mut().zip(sline.chunks_exact(3)) {
+                *d = s[offset];
+            }
+        }
+    }
+}
+
+/// Merges the planes of a `GBRP` picture into `RGB24` pixels.
+///
+/// The source planes are given in storage order.
+pub fn gbrp_to_rgb24(
+    src: [&[u8]; 3],
+    src_strides: [usize; 3],
+    dst: &mut [u8],
+    dst_stride: usize,
+    width: usize,
+    height: usize,
+) {
+    for (offset, plane) in rgb_layout() {
+        let lines = src[plane].chunks(src_strides[plane]);
+        for (dline, sline) in dst.chunks_mut(dst_stride).zip(lines).take(height) {
+            for (d, s) in dline.chunks_exact_mut(3).zip(&sline[..width]) {
+                d[offset] = *s;
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1167,6 +1488,111 @@ mod test {
             println!("formaton rgba64- {}", formats::RGBA64);
         }
 
+        #[test]
+        fn all() {
+            assert!(formats::all().contains(&formats::YUV420));
+            assert_eq!(formats::all().len(), 18);
+        }
+
+        #[test]
+        fn short_name() {
+            assert_eq!(formats::YUV420.short_name(), "yuv420p");
+            assert_eq!(formats::YUV422.short_name(), "yuv422p");
+            assert_eq!(formats::YUV420_10.short_name(), "yuv420p10le");
+            assert_eq!(formats::RGBA.short_name(), "rgba");
+            assert_eq!(formats::RGB565.short_name(), "rgb565le");
+            assert_eq!(formats::PAL8.short_name(), "pal8");
+            assert_eq!(formats::GBRP.short_name(), "gbrp");
+        }
+
+        #[test]
+        fn short_name_rgb_order() {
+            assert_eq!(formats::RGB24.short_name(), "rgb24");
+            assert_eq!(formats::RGB48.short_name(), "rgb48le");
+
+            let bgr = Formaton::new(
+                ColorModel::Trichromatic(TrichromaticEncodingSystem::RGB),
+                &[
+                    Chromaton::packrgb(8, 0, 0, 3),
+                    Chromaton::packrgb(8, 0, 1, 3),
+                    Chromaton::packrgb(8, 0, 2, 3),
+                ],
+                3,
+                false,
+                false,
+                false,
+            );
+            assert_ne!(bgr.short_name(), "rgb24");
+            assert_eq!(bgr.short_name(), bgr.to_string());
+        }
+
+        #[test]
+        fn effective_chroma_location() {
+            let mut yuv420 = *formats::YUV420;
+            yuv420.chroma_location = ChromaLocation::Left;
+            assert_eq!(yuv420.effective_chroma_location(), ChromaLocation::Left);
+
+            let mut yuv444 = *formats::YUV444;
+            yuv444.chroma_location = ChromaLocation::Left;
+            assert_eq!(
+                yuv444.effective_chroma_location(),
+                ChromaLocation::Unspecified
+            );
+
+            let mut rgb24 = *formats::RGB24;
+            rgb24.chroma_location = ChromaLocation::Center;
+            assert_eq!(
+                rgb24.effective_chroma_location(),
+                ChromaLocation::Unspecified
+            );
+        }
+
+        #[test]
+        fn builders() {
+            assert_eq!(Formaton::planar_yuv(8, 1, 1, false), *formats::YUV420);
+            assert_eq!(Formaton::planar_yuv(8, 0, 0, false), *formats::YUV444);
+            assert_eq!(Formaton::planar_yuv(10, 2, 1, false), *formats::YUV410_10);
+
+            let yuva = Formaton::planar_yuv(8, 1, 0, true);
+            assert_eq!(yuva.get_num_comp(), 4);
+            assert!(yuva.has_alpha());
+
+            assert_eq!(Formaton::packed_rgb(8, false, false), *formats::RGB24);
+            assert_eq!(Formaton::packed_rgb(8, true, false), *formats::RGBA);
+            assert_eq!(Formaton::packed_rgb(16, false, false), *formats::RGB48);
+            assert_eq!(Formaton::packed_rgb(16, true, false), *formats::RGBA64);
+        }
+
+        #[test]
+        fn color_info() {
+            let info = ColorInfo {
+                primaries: ColorPrimaries::BT2020,
+                xfer: TransferCharacteristic::PerceptualQuantizer,
+                matrix: MatrixCoefficients::BT2020NonConstantLuminance,
+                chroma_location: ChromaLocation::TopLeft,
+            };
+
+            let mut fmt = *formats::YUV420_10;
+            fmt.apply_color_info(&info);
+            assert_eq!(fmt.get_primaries(), info.primaries);
+            assert_eq!(fmt.get_xfer(), info.xfer);
+            assert_eq!(fmt.get_matrix(), info.matrix);
+            assert_eq!(fmt.effective_chroma_location(), info.chroma_location);
+
+            let built = formats::YUV420_10.with_color_info(
+                info.primaries,
+                info.xfer,
+                info.matrix,
+                info.chroma_location,
+            );
+            assert_eq!(built, fmt);
+
+            fmt.set_primaries(ColorPrimaries::BT709);
+            assert_eq!(fmt.get_primaries(), ColorPrimaries::BT709);
+            assert_eq!(fmt.set_matrix_from_u32(1), Some(MatrixCoefficients::BT709));
+            assert_eq!(fmt.get_matrix(), MatrixCoefficients::BT709);
+        }
+
         #[test]
         fn comparison() {
             use std::sync::Arc;
@@ -1181,5 +1607,69 @@ mod test {
                 panic!("rcf");
             }
         }
+
+        #[test]
+        fn yuv422_subsampling() {
+            for fmt in [formats::YUV422, formats::YUV422_10] {
+                for c in fmt.iter().flatten().skip(1) {
+                    assert_eq!((c.get_width(16), c.get_height(16)), (8, 16));
+                }
+            }
+        }
+    }
+
+    mod repack {
+        use super::super::*;
+
+        #[test]
+        fn rgb24_gbrp_roundtrip() {
+            let (width, height) = (5, 3);
+            let (rgb_stride, plane_stride) = (16, 8);
+            let rgb = (0..rgb_stride * height)
+                .map(|i| (i * 7) as u8)
+                .collect::<Vec<_>>();
+
+            let mut planes = vec![vec![0u8; plane_stride * height]; 3];
+            let [g, b, r] = &mut planes[..] else {
+                unreachable!()
+            };
+            rgb24_to_gbrp(
+                &rgb,
+                rgb_stride,
+                [g, b, r],
+                [plane_stride; 3],
+                width,
+                height,
+            );
+
+            // The red component is stored at offset 2 in RGB24
+            assert_eq!(planes[2][plane_stride + 1], rgb[rgb_stride + 3 + 2]);
+            assert_eq!(planes[0][1], rgb[3 + 1]);
+            assert_eq!(planes[1][1], rgb[3]);
+
+            let mut out = vec![0u8; rgb_stride * height];
+            let src = [&planes[0][..], &planes[1][..], &planes[2][..]];
+            gbrp_to_rgb24(src, [plane_stride; 3], &mut out, rgb_stride, width, height);
+
+            for (a, b) in out.chunks(rgb_stride).zip(rgb.chunks(rgb_stride)) {
+                assert_eq!(a[..width * 3], b[..width * 3]);
+            }
+        }
+    }
+
+    mod chromaton {
+        use super::super::*;
+
+        #[test]
+        fn rounding() {
+            let c = Chromaton::yuv8(1, 1, 1);
+
+            assert_eq!(c.get_width(3), 2);
+            assert_eq!(c.get_width_rounded(3, true), 2);
+            assert_eq!(c.get_width_rounded(3, false), 1);
+            assert_eq!(c.get_height_rounded(5, false), 2);
+            assert_eq!(c.get_linesize_rounded(65, 1, true), 33);
+            assert_eq!(c.get_linesize_rounded(65, 1, false), 32);
+        }
     }
 }
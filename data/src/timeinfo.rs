@@ -1,6 +1,7 @@
 //! Time info definitions for frames and packets.
 
-use crate::rational::Rational64;
+use crate::rational::{Ratio, Rational64};
+use num_traits::{Signed, Zero};
 use std::any::Any;
 use std::sync::Arc;
 
@@ -21,3 +22,131 @@ pub struct TimeInfo {
     /// Timebase user private data.
     pub user_private: Option<Arc<dyn Any + Send + Sync>>,
 }
+
+impl TimeInfo {
+    /// Builds the timestamp information of the frame following `prev`,
+    /// assuming a constant frame rate.
+    ///
+    /// The presentation timestamp is advanced by one frame duration expressed
+    /// in the timebase of `prev`, or by a single unit if no timebase is set.
+    ///
+    /// Returns `None` if `frame_rate` or the timebase of `prev` is not
+    /// positive.
+    pub fn interpolate(prev: &TimeInfo, frame_rate: Rational64) -> Option<TimeInfo> {
+        if !frame_rate.is_positive() {
+            return None;
+        }
+        let step = match prev.timebase {
+            Some(tb) if tb.is_positive() => (frame_rate * tb).recip().round().to_integer(),
+            Some(_) => return None,
+            None => 1,
+        };
+
+        Some(TimeInfo {
+            pts: prev.pts.map(|pts| pts + step),
+            dts: prev.dts.map(|dts| dts + step),
+            duration: Some(step as u64),
+            timebase: prev.timebase,
+            user_private: None,
+        })
+    }
+
+    /// Converts the timestamps and the duration to the timebase `to`,
+    /// rounding to the nearest value, and sets `to` as the new timebase.
+    ///
+    /// Nothing is done if no timebase is set or if `to` is zero, since
+    /// the timestamps cannot be converted. Values out of range saturate.
+    pub fn rescale(&mut self, to: Rational64) {
+        let from = match self.timebase {
+            Some(from) if !to.is_zero() => from,
+            _ => return,
+        };
+        let factor = Ratio::new(
+            *from.numer() as i128 * *to.denom() as i128,
+            *from.denom() as i128 * *to.numer() as i128,
+        );
+        let scale = |v: i128| -> i128 {
+            match v.checked_mul(*factor.numer()) {
+                Some(num) => Ratio::new(num, *factor.denom()).round().to_integer(),
+                None if (v < 0) == (*factor.numer() < 0) => i128::MAX,
+                None => i128::MIN,
+            }
+        };
+
+        let clamp = |v: i128| v.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+        self.pts = self.pts.map(|pts| clamp(scale(pts as i128)));
+        self.dts = self.dts.map(|dts| clamp(scale(dts as i128)));
+        self.duration = self
+            .duration
+            .map(|d| scale(d as i128).clamp(0, u64::MAX as i128) as u64);
+        self.timebase = Some(to);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interpolate() {
+        let mut t = TimeInfo {
+            pts: Some(0),
+            timebase: Some(Rational64::new(1, 1000)),
+            ..Default::default()
+        };
+        let mut pts = vec![t.pts.unwrap()];
+
+        for _ in 0..4 {
+            t = TimeInfo::interpolate(&t, Rational64::from_integer(25)).unwrap();
+            pts.push(t.pts.unwrap());
+        }
+
+        assert_eq!(pts, [0, 40, 80, 120, 160]);
+        assert!(TimeInfo::interpolate(&t, Rational64::from_integer(0)).is_none());
+        assert!(TimeInfo::interpolate(&t, Rational64::new(-25, 1)).is_none());
+    }
+
+    #[test]
+    fn rescale() {
+        let mut t = TimeInfo {
+            pts: Some(1001),
+            dts: Some(-3),
+            duration: Some(33),
+            timebase: Some(Rational64::new(1, 1000)),
+            ..Default::default()
+        };
+
+        t.rescale(Rational64::new(1, 90000));
+        assert_eq!(
+            (t.pts, t.dts, t.duration),
+            (Some(90090), Some(-270), Some(2970))
+        );
+
+        t.rescale(Rational64::new(1, 25));
+        assert_eq!((t.pts, t.dts, t.duration), (Some(25), Some(0), Some(1)));
+        assert_eq!(t.timebase, Some(Rational64::new(1, 25)));
+
+        let mut t = TimeInfo {
+            pts: Some(10),
+            ..Default::default()
+        };
+        t.rescale(Rational64::new(1, 25));
+        assert_eq!((t.pts, t.timebase), (Some(10), None));
+
+        let mut t = TimeInfo {
+            pts: Some(i64::MAX),
+            dts: Some(i64::MIN),
+            duration: Some(u64::MAX),
+            timebase: Some(Rational64::new(i64::MAX, 1)),
+            ..Default::default()
+        };
+        t.rescale(Rational64::new(0, 1));
+        assert_eq!(t.timebase, Some(Rational64::new(i64::MAX, 1)));
+
+        t.rescale(Rational64::new(1, i64::MAX));
+        assert_eq!(
+            (t.pts, t.dts, t.duration),
+            (Some(i64::MAX), Some(i64::MIN), Some(u64::MAX))
+        );
+    }
+}
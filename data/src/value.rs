@@ -25,6 +25,40 @@ pub enum Value<'a> {
     Soniton(Arc<Soniton>),
 }
 
+/// Kind of an option value, without the value itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// Signed integer value.
+    I64,
+    /// Unsigned integer value.
+    U64,
+    /// Unicode string value.
+    Str,
+    /// Boolean value.
+    Bool,
+    /// Pair of signed integer values.
+    Pair,
+    /// Image colorspace representation value.
+    Formaton,
+    /// Audio format definition value.
+    Soniton,
+}
+
+impl<'a> Value<'a> {
+    /// Returns the kind of this value.
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::I64(_) => ValueKind::I64,
+            Value::U64(_) => ValueKind::U64,
+            Value::Str(_) => ValueKind::Str,
+            Value::Bool(_) => ValueKind::Bool,
+            Value::Pair(..) => ValueKind::Pair,
+            Value::Formaton(_) => ValueKind::Formaton,
+            Value::Soniton(_) => ValueKind::Soniton,
+        }
+    }
+}
+
 impl<'a> From<i64> for Value<'a> {
     fn from(v: i64) -> Self {
         Value::I64(v)
@@ -8,7 +8,6 @@ use crate::buffer::Buffered;
 use std::cmp;
 use std::io;
 use std::io::{BufRead, Read, Result, Seek, SeekFrom};
-use std::iter;
 use std::iter::Iterator;
 
 /// Partial consumption buffer for any reader.
@@ -19,6 +18,8 @@ pub struct AccReader<R> {
     end: usize,
     // Position in the stream of the buffer's beginning
     index: usize,
+    // Alignment of the buffered data after a refill
+    align: usize,
 }
 
 impl<R: Read + Seek> AccReader<R> {
@@ -30,15 +31,34 @@ impl<R: Read + Seek> AccReader<R> {
     /// Creates a new `AccReader` instance of a determined capacity
     /// for a reader.
     pub fn with_capacity(cap: usize, inner: R) -> AccReader<R> {
+        AccReader::with_aligned_capacity(cap, 1, inner)
+    }
+
+    /// Creates a new `AccReader` instance of a determined capacity
+    /// for a reader, keeping the buffered data aligned to `align` bytes.
+    ///
+    /// The buffer is over-allocated by `align - 1` bytes, so that the
+    /// data read by `fill_buf` always starts at an `align`-aligned address.
+    ///
+    /// `align` must be a power of two.
+    pub fn with_aligned_capacity(cap: usize, align: usize, inner: R) -> AccReader<R> {
+        assert!(align.is_power_of_two());
+
         AccReader {
             inner,
-            buf: iter::repeat(0).take(cap).collect::<Vec<_>>(),
+            buf: std::iter::repeat_n(0, cap + align - 1).collect::<Vec<_>>(),
             pos: 0,
             end: 0,
             index: 0,
+            align,
         }
     }
 
+    // Offset of the first aligned byte of the buffer
+    fn base(&self) -> usize {
+        self.buf.as_ptr().align_offset(self.align)
+    }
+
     /// Gets a reference to the underlying reader.
     pub fn get_ref(&self) -> &R {
         &self.inner
@@ -56,6 +76,16 @@ impl<R: Read + Seek> AccReader<R> {
         self.inner
     }
 
+    /// Replaces the underlying reader, keeping the internal buffer allocation.
+    ///
+    /// Note that any leftover data in the internal buffer is lost.
+    pub fn reset(&mut self, inner: R) {
+        self.inner = inner;
+        self.pos = 0;
+        self.end = 0;
+        self.index = 0;
+    }
+
     /// Resets the buffer to the current position.
     ///
     /// All data before the current position is lost.
@@ -65,12 +95,13 @@ impl<R: Read + Seek> AccReader<R> {
             self.pos,
             self.end
         );
-        if self.end - self.pos > 0 {
+        let base = self.base();
+        if self.end - self.pos > 0 && self.pos != base {
             log::trace!("copying {} to beginning of buffer", self.end - self.pos);
-            self.buf.copy_within(self.pos..self.end, 0);
+            self.buf.copy_within(self.pos..self.end, base);
         }
-        self.end -= self.pos;
-        self.pos = 0;
+        self.end = self.end - self.pos + base;
+        self.pos = base;
     }
 
     /// Returns buffer data.
@@ -79,6 +110,18 @@ impl<R: Read + Seek> AccReader<R> {
         &self.buf[self.pos..self.end]
     }
 
+    /// Returns buffer data, starting at an address aligned as requested
+    /// by `with_aligned_capacity`.
+    ///
+    /// If consuming data moved the start of the buffer off the alignment,
+    /// the pending data is first moved back to an aligned position.
+    pub fn aligned_data(&mut self) -> &[u8] {
+        if self.pos != self.base() {
+            self.reset_buffer_position();
+        }
+        &self.buf[self.pos..self.end]
+    }
+
     /// Returns buffer capacity.
     pub fn capacity(&self) -> usize {
         self.end - self.pos
@@ -90,9 +133,16 @@ impl<R: Read + Seek + Send + Sync> Buffered for AccReader<R> {
         &self.buf[self.pos..self.end]
     }
     fn grow(&mut self, len: usize) {
-        let l = self.buf.len() + len;
+        // Reallocating might change the alignment offset
+        let l = self.buf.len() + len + self.align - 1;
         self.buf.resize(l, 0);
     }
+    fn consumed_total(&self) -> u64 {
+        self.index as u64
+    }
+    fn inner_position(&mut self) -> io::Result<u64> {
+        self.inner.stream_position()
+    }
 }
 
 impl<R: Read + Seek> Read for AccReader<R> {
@@ -142,7 +192,7 @@ impl<R: Read + Seek> Read for AccReader<R> {
 impl<R: Read + Seek> BufRead for AccReader<R> {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
         // trace!("fillbuf current: {:?}", str::from_utf8(&self.buf[self.pos..self.end]).unwrap());
-        if self.pos != 0 || self.end != self.buf.len() {
+        if self.pos != self.base() || self.end != self.buf.len() {
             self.reset_buffer_position();
             log::trace!("buffer reset ended");
             let read = self.inner.read(&mut self.buf[self.end..])?;
@@ -311,6 +361,59 @@ mod tests {
         assert_eq!(4, acc.lines().count());
     }
 
+    #[test]
+    fn reset() {
+        let mut acc = AccReader::with_capacity(4, Cursor::new(&b"abcdef"[..]));
+        let mut buf = [0u8; 3];
+
+        acc.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"abc");
+
+        acc.reset(Cursor::new(&b"ghijkl"[..]));
+        assert_eq!(0, acc.stream_position().unwrap());
+        assert!(acc.data().is_empty());
+
+        acc.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ghi");
+        acc.fill_buf().unwrap();
+        assert_eq!(b"jkl", acc.data());
+        assert_eq!(acc.buf.len(), 4);
+    }
+
+    #[test]
+    fn aligned_data() {
+        let buf = (0u8..).take(255).collect::<Vec<u8>>();
+        let mut acc = AccReader::with_aligned_capacity(16, 64, Cursor::new(&buf[..]));
+
+        acc.fill_buf().unwrap();
+        let data = acc.aligned_data();
+        assert_eq!(data.as_ptr() as usize % 64, 0);
+        assert!(data.len() >= 16);
+        assert_eq!(&buf[..data.len()], data);
+
+        acc.consume(3);
+        let data = acc.aligned_data();
+        assert_eq!(data.as_ptr() as usize % 64, 0);
+        assert_eq!(&buf[3..3 + data.len()], data);
+
+        acc.grow(100);
+        acc.fill_buf().unwrap();
+        let data = acc.aligned_data();
+        assert_eq!(data.as_ptr() as usize % 64, 0);
+        assert!(data.len() >= 100);
+        assert_eq!(&buf[3..3 + data.len()], data);
+    }
+
+    #[test]
+    fn positions() {
+        let mut acc = AccReader::with_capacity(4, Cursor::new(b"0123456789"));
+
+        acc.fill_buf().unwrap();
+        acc.consume(3);
+        assert_eq!(acc.consumed_total(), 3);
+        assert_eq!(acc.inner_position().unwrap(), 4);
+    }
+
     #[test]
     fn grow() {
         let buf = b"abcdefghilmnopqrst";
@@ -2,7 +2,7 @@ mod accreader;
 
 pub use self::accreader::AccReader;
 
-use std::io::{BufRead, Seek};
+use std::io::{self, BufRead, Seek};
 
 /// Used to interact with a buffer.
 pub trait Buffered: BufRead + Seek + Send + Sync {
@@ -10,4 +10,14 @@ pub trait Buffered: BufRead + Seek + Send + Sync {
     fn data(&self) -> &[u8];
     /// Increases the size of a buffer.
     fn grow(&mut self, len: usize);
+    /// Returns the number of bytes consumed from the start of the stream.
+    fn consumed_total(&self) -> u64;
+    /// Returns the position of the wrapped reader.
+    ///
+    /// It is ahead of `consumed_total` by the amount of data buffered
+    /// but not consumed yet. The default implementation returns the
+    /// stream position, which is correct for unbuffered implementors.
+    fn inner_position(&mut self) -> io::Result<u64> {
+        self.stream_position()
+    }
 }
@@ -1,4 +1,11 @@
+use std::path::Path;
+
+use av_bitstream::byteread::*;
+use av_bitstream::bytewrite::*;
+
 use crate::data::rational::Rational64;
+use crate::demuxer::{Descriptor, Probe};
+use crate::error::*;
 use crate::stream::Stream;
 
 /// Global media file information.
@@ -30,4 +37,520 @@ impl GlobalInfo {
 
         idx
     }
+
+    /// Appends the media described by `other` to the current one.
+    ///
+    /// Both must have the same timebase and the same streams, with matching
+    /// codec parameters and timebases. The durations are summed, and become
+    /// unknown if either of them is or if their sum overflows.
+    pub fn concat(&mut self, other: &GlobalInfo) -> Result<()> {
+        if self.timebase != other.timebase || self.streams.len() != other.streams.len() {
+            return Err(Error::InvalidData);
+        }
+
+        let compatible = self
+            .streams
+            .iter()
+            .zip(other.streams.iter())
+            .all(|(a, b)| a.params == b.params && a.timebase == b.timebase);
+        if !compatible {
+            return Err(Error::InvalidData);
+        }
+
+        self.duration = sum_durations(self.duration, other.duration);
+        for (st, ost) in self.streams.iter_mut().zip(other.streams.iter()) {
+            st.duration = sum_durations(st.duration, ost.duration);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of packet bytes observed so far across
+    /// all streams.
+    pub fn coded_size(&self) -> u64 {
+        self.streams
+            .iter()
+            .map(|st| st.observed_stats.total_bytes)
+            .sum()
+    }
+}
+
+fn sum_durations(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    a?.checked_add(b?)
+}
+
+/// Seek index entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// Timestamp of the entry.
+    pub timestamp: i64,
+    /// Byte offset of the entry in the source.
+    pub offset: u64,
+    /// Tells whether the entry points to a keyframe.
+    pub is_key: bool,
+}
+
+// Serialized size of an entry: timestamp, offset and flags.
+const INDEX_ENTRY_SIZE: usize = 8 + 8 + 1;
+
+/// Seek index, mapping timestamps to byte offsets.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Index {
+    entries: Vec<IndexEntry>,
+}
+
+impl Index {
+    /// Creates a new empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry to the index, keeping the entries sorted by timestamp.
+    pub fn add_entry(&mut self, entry: IndexEntry) {
+        let pos = self
+            .entries
+            .partition_point(|e| e.timestamp <= entry.timestamp);
+
+        self.entries.insert(pos, entry);
+    }
+
+    /// Returns the entries sorted by timestamp.
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Returns the nearest keyframe entry at or before `timestamp`.
+    pub fn lookup(&self, timestamp: i64) -> Option<&IndexEntry> {
+        let end = self.entries.partition_point(|e| e.timestamp <= timestamp);
+
+        self.entries[..end].iter().rev().find(|e| e.is_key)
+    }
+
+    /// Serializes the index as a big-endian entry count followed by
+    /// the entries.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0; 4 + self.entries.len() * INDEX_ENTRY_SIZE];
+
+        put_u32b(&mut buf, self.entries.len() as u32);
+        for (e, chunk) in self
+            .entries
+            .iter()
+            .zip(buf[4..].chunks_mut(INDEX_ENTRY_SIZE))
+        {
+            put_i64b(chunk, e.timestamp);
+            put_u64b(&mut chunk[8..], e.offset);
+            put_u8(&mut chunk[16..], e.is_key as u8);
+        }
+
+        buf
+    }
+
+    /// Deserializes an index produced by `to_bytes`.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self> {
+        if buf.len() < 4 {
+            return Err(Error::InvalidData);
+        }
+        let count = get_u32b(buf) as usize;
+        let data = &buf[4..];
+        if count.checked_mul(INDEX_ENTRY_SIZE) != Some(data.len()) {
+            return Err(Error::InvalidData);
+        }
+
+        let mut index = Index::new();
+        for chunk in data.chunks(INDEX_ENTRY_SIZE) {
+            index.add_entry(IndexEntry {
+                timestamp: get_i64b(chunk),
+                offset: get_u64b(&chunk[8..]),
+                is_key: get_u8(&chunk[16..]) != 0,
+            });
+        }
+
+        Ok(index)
+    }
+}
+
+/// Frame rate classification of a video stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameRateMode {
+    /// Constant frame rate, in frames per second.
+    Cfr(Rational64),
+    /// Variable frame rate.
+    Vfr,
+}
+
+/// Classifies a video stream as constant or variable frame rate
+/// from the `dts` of its packets.
+#[derive(Debug, Clone)]
+pub struct FrameRateDetector {
+    timebase: Rational64,
+    window: usize,
+    tolerance: i64,
+    last_dts: Option<i64>,
+    deltas: Vec<i64>,
+}
+
+impl FrameRateDetector {
+    /// Creates a detector that classifies the stream after `window`
+    /// timestamp differences, expressed in `timebase` units.
+    ///
+    /// Differences deviating from their mean by at most `tolerance`
+    /// units are still considered constant.
+    pub fn new(timebase: Rational64, window: usize, tolerance: i64) -> Self {
+        FrameRateDetector {
+            timebase,
+            window: window.max(1),
+            tolerance,
+            last_dts: None,
+            deltas: Vec::with_capacity(window),
+        }
+    }
+
+    /// Ingests the `dts` of the next packet.
+    ///
+    /// Timestamps received once the window is full are ignored.
+    pub fn push(&mut self, dts: i64) {
+        if self.deltas.len() >= self.window {
+            return;
+        }
+        if let Some(last) = self.last_dts {
+            self.deltas.push(dts - last);
+        }
+        self.last_dts = Some(dts);
+    }
+
+    /// Returns the classification, or `None` if the window is not full yet.
+    pub fn mode(&self) -> Option<FrameRateMode> {
+        if self.deltas.len() < self.window {
+            return None;
+        }
+
+        let n = self.deltas.len() as i64;
+        let sum: i64 = self.deltas.iter().sum();
+        let constant = self
+            .deltas
+            .iter()
+            .all(|&d| d > 0 && (d * n - sum).abs() <= self.tolerance * n);
+
+        if !constant {
+            return Some(FrameRateMode::Vfr);
+        }
+
+        let rate = Rational64::new(n * *self.timebase.denom(), sum * *self.timebase.numer());
+
+        Some(FrameRateMode::Cfr(rate))
+    }
+}
+
+/// Defines a series of methods to interact with a list of format descriptors.
+pub trait FormatList: Sized {
+    /// The type of the structure used to describe a format.
+    type D: ?Sized;
+
+    /// Creates a new format list.
+    fn new() -> Self;
+
+    /// Search by name whether a format descriptor is in the format list and
+    /// returns it.
+    ///
+    /// If the requested format descriptor is not in the list,
+    /// `None` is returned.
+    fn by_name(&self, name: &str) -> Option<&'static Self::D>;
+
+    /// Appends a format to the list.
+    fn append(&mut self, desc: &'static Self::D);
+
+    /// Creates a new format list starting from a list of format descriptors.
+    fn from_list(descs: &[&'static Self::D]) -> Self {
+        let mut f = Self::new();
+        for &desc in descs {
+            f.append(desc);
+        }
+
+        f
+    }
+}
+
+/// Detects the format of a file from its content, falling back to
+/// its extension when the content probing is not conclusive.
+///
+/// Among the formats matching the extension, the one scoring the highest
+/// on the content is preferred.
+pub fn probe_path<T: Descriptor + ?Sized>(
+    path: &Path,
+    data: &[u8],
+    demuxers: &[&'static T],
+) -> Option<&'static T> {
+    demuxers.probe(data).or_else(|| {
+        let ext = path.extension()?.to_str()?;
+
+        demuxers
+            .iter()
+            .filter(|d| {
+                d.describe()
+                    .extensions
+                    .iter()
+                    .any(|e| e.eq_ignore_ascii_case(ext))
+            })
+            .max_by_key(|d| d.probe(data))
+            .copied()
+    })
+}
+
+/// Byte pattern identifying a format.
+#[derive(Clone, Copy, Debug)]
+pub struct Magic {
+    /// Name of the format descriptor.
+    pub name: &'static str,
+    /// Position of the pattern from the start of the data.
+    pub offset: usize,
+    /// Bytes to match.
+    pub pattern: &'static [u8],
+    /// Mask applied to the data before comparing it with the pattern.
+    ///
+    /// Bytes not covered by the mask are compared as they are.
+    pub mask: Option<&'static [u8]>,
+}
+
+impl Magic {
+    /// Tells whether `data` contains the pattern.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        let end = match self.offset.checked_add(self.pattern.len()) {
+            Some(end) if end <= data.len() => end,
+            _ => return false,
+        };
+        let mask = self.mask.unwrap_or(&[]);
+
+        data[self.offset..end]
+            .iter()
+            .zip(self.pattern)
+            .enumerate()
+            .all(|(i, (&b, &p))| {
+                let m = mask.get(i).copied().unwrap_or(0xff);
+                b & m == p & m
+            })
+    }
+}
+
+/// A list of magic numbers, checked in order.
+pub type MagicTable = [Magic];
+
+/// Returns the name of the format whose magic number is found first
+/// in `data`, if any.
+pub fn match_magic(data: &[u8], table: &MagicTable) -> Option<&'static str> {
+    table.iter().find(|m| m.matches(data)).map(|m| m.name)
+}
+
+// Size of a well-formed ID3v2 tag starting at the beginning of `data`.
+fn id3v2_size(data: &[u8]) -> Option<usize> {
+    let header = data.get(..10)?;
+    if &header[..3] != b"ID3" || !(2..=4).contains(&header[3]) || header[4] == 0xff {
+        return None;
+    }
+    let size = &header[6..10];
+    if size.iter().any(|&b| b & 0x80 != 0) {
+        return None;
+    }
+    let size = size.iter().fold(0, |acc, &b| (acc << 7) | b as usize);
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+
+    Some(10 + size + footer)
+}
+
+// Size of a well-formed APEv2 tag header and its items starting at
+// the beginning of `data`.
+fn apev2_size(data: &[u8]) -> Option<usize> {
+    const IS_HEADER: u32 = 1 << 29;
+
+    let header = data.get(..32)?;
+    if &header[..8] != b"APETAGEX" || !matches!(get_u32l(&header[8..]), 1000 | 2000) {
+        return None;
+    }
+    if get_u32l(&header[20..]) & IS_HEADER == 0 {
+        return None;
+    }
+
+    Some(32 + get_u32l(&header[12..]) as usize)
+}
+
+/// Returns the offset of the data following the ID3v2 and APEv2 tags
+/// found at the beginning of `data`, so probing can start from it.
+///
+/// Only well-formed tags fully contained in `data` are skipped.
+pub fn skip_leading_tags(data: &[u8]) -> usize {
+    let mut offset = 0;
+
+    while let Some(size) = id3v2_size(&data[offset..]).or_else(|| apev2_size(&data[offset..])) {
+        if size > data.len() - offset {
+            break;
+        }
+        offset += size;
+    }
+
+    offset
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::params::CodecParams;
+
+    fn info(codec_id: &str) -> GlobalInfo {
+        let params = CodecParams {
+            kind: None,
+            codec_id: Some(codec_id.to_owned()),
+            extradata: None,
+            bit_rate: 0,
+            convergence_window: 0,
+            delay: 0,
+        };
+        let mut st = Stream::from_params(&params, Rational64::new(1, 1000));
+        st.duration = Some(5000);
+
+        let mut info = GlobalInfo {
+            duration: Some(5000),
+            timebase: Some(Rational64::new(1, 1000)),
+            streams: Vec::new(),
+        };
+        info.add_stream(st);
+        info
+    }
+
+    #[test]
+    fn index() {
+        let mut index = Index::new();
+        for (timestamp, offset, is_key) in [(0, 0, true), (40, 900, false), (80, 1500, true)] {
+            index.add_entry(IndexEntry {
+                timestamp,
+                offset,
+                is_key,
+            });
+        }
+        index.add_entry(IndexEntry {
+            timestamp: 20,
+            offset: 400,
+            is_key: false,
+        });
+
+        assert_eq!(index.entries()[1].timestamp, 20);
+        assert_eq!(index.lookup(-1), None);
+        assert_eq!(index.lookup(0).unwrap().offset, 0);
+        assert_eq!(index.lookup(79).unwrap().offset, 0);
+        assert_eq!(index.lookup(80).unwrap().offset, 1500);
+        assert_eq!(index.lookup(1000).unwrap().offset, 1500);
+
+        let bytes = index.to_bytes();
+        assert_eq!(bytes.len(), 4 + 4 * 17);
+        assert_eq!(Index::from_bytes(&bytes).unwrap(), index);
+        assert!(Index::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Index::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn concat() {
+        let mut a = info("dummy");
+        a.concat(&info("dummy")).unwrap();
+
+        assert_eq!(a.duration, Some(10000));
+        assert_eq!(a.timebase, Some(Rational64::new(1, 1000)));
+        assert_eq!(a.streams.len(), 1);
+        assert_eq!(a.streams[0].duration, Some(10000));
+
+        assert!(a.concat(&info("other")).is_err());
+        assert_eq!(a.duration, Some(10000));
+
+        let mut b = info("dummy");
+        b.duration = Some(u64::MAX);
+        a.concat(&b).unwrap();
+        assert_eq!(a.duration, None);
+        assert_eq!(a.streams[0].duration, Some(15000));
+    }
+
+    #[test]
+    fn frame_rate() {
+        let timebase = Rational64::new(1, 90000);
+
+        let mut cfr = FrameRateDetector::new(timebase, 4, 1);
+        for dts in [0, 3003, 6006, 9010, 12012] {
+            assert_eq!(cfr.mode(), None);
+            cfr.push(dts);
+        }
+        assert_eq!(
+            cfr.mode(),
+            Some(FrameRateMode::Cfr(Rational64::new(30000, 1001)))
+        );
+
+        let mut vfr = FrameRateDetector::new(timebase, 4, 1);
+        for dts in [0, 3003, 6006, 12012, 15015] {
+            vfr.push(dts);
+        }
+        assert_eq!(vfr.mode(), Some(FrameRateMode::Vfr));
+    }
+
+    #[test]
+    fn coded_size() {
+        let mut a = info("dummy");
+        let st = a.streams[0].clone();
+        a.add_stream(st);
+        assert_eq!(a.coded_size(), 0);
+
+        for (idx, size) in [(0, 100), (1, 20), (0, 300), (1, 5)] {
+            a.streams[idx].observed_stats.update(size);
+        }
+
+        assert_eq!(a.coded_size(), 425);
+    }
+
+    #[test]
+    fn magic() {
+        const TABLE: &MagicTable = &[
+            Magic {
+                name: "dummy",
+                offset: 0,
+                pattern: b"dummy",
+                mask: None,
+            },
+            Magic {
+                name: "masked",
+                offset: 4,
+                pattern: &[0xf0, 0x0a],
+                mask: Some(&[0xf0]),
+            },
+        ];
+
+        assert_eq!(match_magic(b"dummy content", TABLE), Some("dummy"));
+        assert_eq!(match_magic(b"dumm", TABLE), None);
+        assert_eq!(match_magic(b"xxxx\xf7\x0a", TABLE), Some("masked"));
+        assert_eq!(match_magic(b"xxxx\xe7\x0a", TABLE), None);
+        assert_eq!(match_magic(b"xxxx\xf7\x0b", TABLE), None);
+    }
+
+    #[test]
+    fn leading_tags() {
+        let content = b"dummy content";
+        assert_eq!(skip_leading_tags(content), 0);
+
+        // ID3v2.4 tag with a syncsafe size of 0x101 bytes
+        let mut data = b"ID3\x04\x00\x00\x00\x00\x02\x01".to_vec();
+        data.extend_from_slice(&[0; 0x101]);
+        data.extend_from_slice(content);
+        assert_eq!(skip_leading_tags(&data), 10 + 0x101);
+        assert_eq!(&data[skip_leading_tags(&data)..], content);
+
+        // Truncated tag
+        assert_eq!(skip_leading_tags(&data[..100]), 0);
+
+        // Size not syncsafe
+        let mut bad = data.clone();
+        bad[8] = 0x82;
+        assert_eq!(skip_leading_tags(&bad), 0);
+
+        // APEv2 header with 16 bytes of items after the ID3v2 tag
+        let mut ape = b"APETAGEX".to_vec();
+        for n in [2000u32, 16, 0, 0xa000_0000] {
+            ape.extend_from_slice(&n.to_le_bytes());
+        }
+        ape.extend_from_slice(&[0; 8 + 16]);
+        data.splice(10 + 0x101..10 + 0x101, ape);
+        assert_eq!(&data[skip_leading_tags(&data)..], content);
+    }
 }
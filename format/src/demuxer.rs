@@ -2,12 +2,17 @@ use crate::error::*;
 
 use crate::buffer::Buffered;
 use std::any::Any;
-use std::io::SeekFrom;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, SeekFrom};
 use std::sync::Arc;
 
 use crate::common::*;
 
+use av_bitstream::codebook::CodebookMode;
+
 use crate::data::packet::Packet;
+use crate::data::timeinfo::TimeInfo;
+use crate::rational::Rational64;
 use crate::stream::Stream;
 
 /// Events processed by a demuxer analyzing a source.
@@ -30,6 +35,30 @@ pub enum Event {
     Eof,
 }
 
+/// Creates a packet from the first `len` bytes of the buffered data.
+///
+/// The buffer is left untouched, it is up to the demuxer to consume
+/// the data. If fewer than `len` bytes are buffered, the amount of missing
+/// bytes is reported through `Error::MoreDataNeeded`.
+pub fn packet_from_buffered(
+    buf: &dyn Buffered,
+    len: usize,
+    stream_index: isize,
+    t: TimeInfo,
+) -> Result<Packet> {
+    let data = buf.data();
+    if data.len() < len {
+        return Err(Error::MoreDataNeeded(len - data.len()));
+    }
+
+    Ok(Packet {
+        data: data[..len].to_vec(),
+        t,
+        stream_index,
+        ..Packet::new()
+    })
+}
+
 /// Used to implement demuxing operations.
 pub trait Demuxer: Send + Sync {
     /// Reads stream headers and global information from a data structure
@@ -39,6 +68,21 @@ pub trait Demuxer: Send + Sync {
     fn read_headers(&mut self, buf: &mut dyn Buffered, info: &mut GlobalInfo) -> Result<SeekFrom>;
     /// Reads an event from a data structure implementing the `Buffered` trait.
     fn read_event(&mut self, buf: &mut dyn Buffered) -> Result<(SeekFrom, Event)>;
+    /// Extracts the codec extradata carried inline in the first packet
+    /// of a stream.
+    ///
+    /// It is called only when the stream extradata is missing.
+    fn extract_extradata(&mut self, _stream_index: usize, _pkt: &Packet) -> Option<Vec<u8>> {
+        None
+    }
+    /// Returns the number of streams declared by the container, if known.
+    fn expected_stream_count(&self) -> Option<usize> {
+        None
+    }
+    /// Tells whether the demuxer knows that no more streams will be found.
+    fn streams_complete(&self) -> bool {
+        false
+    }
 }
 
 /// Auxiliary structure to encapsulate a demuxer object and
@@ -46,6 +90,9 @@ pub trait Demuxer: Send + Sync {
 pub struct Context<D: Demuxer, R: Buffered> {
     demuxer: D,
     reader: R,
+    frame_rate: Option<Rational64>,
+    last_times: HashMap<isize, TimeInfo>,
+    started_streams: HashSet<isize>,
     /// Global media file information.
     pub info: GlobalInfo,
     /// User private data.
@@ -60,6 +107,9 @@ impl<D: Demuxer, R: Buffered> Context<D, R> {
         Context {
             demuxer,
             reader,
+            frame_rate: None,
+            last_times: HashMap::new(),
+            started_streams: HashSet::new(),
             info: GlobalInfo {
                 duration: None,
                 timebase: None,
@@ -74,6 +124,40 @@ impl<D: Demuxer, R: Buffered> Context<D, R> {
         &self.demuxer
     }
 
+    /// Returns the number of bytes consumed by the demuxer from the
+    /// start of the source.
+    pub fn consumed_total(&self) -> u64 {
+        self.reader.consumed_total()
+    }
+
+    /// Returns the position of the reader wrapped by the buffer.
+    ///
+    /// The wrapped reader is ahead of `consumed_total` by the amount
+    /// of data buffered but not consumed yet.
+    pub fn inner_position(&mut self) -> Result<u64> {
+        Ok(self.reader.inner_position()?)
+    }
+
+    /// Tells whether all the streams of the source were found, either
+    /// because the demuxer says so or because the number of streams
+    /// it declared was reached.
+    pub fn streams_complete(&self) -> bool {
+        self.demuxer.streams_complete()
+            || self
+                .demuxer
+                .expected_stream_count()
+                .is_some_and(|count| self.info.streams.len() >= count)
+    }
+
+    /// Sets the frame rate used to fill in missing packet presentation
+    /// timestamps.
+    ///
+    /// If `None`, packets are returned with the timestamps set by the demuxer.
+    pub fn set_pts_interpolation(&mut self, frame_rate: Option<Rational64>) {
+        self.frame_rate = frame_rate;
+        self.last_times.clear();
+    }
+
     fn read_headers_internal(&mut self) -> Result<()> {
         let demux = &mut self.demuxer;
 
@@ -89,15 +173,28 @@ impl<D: Demuxer, R: Buffered> Context<D, R> {
         }
     }
 
+    // Number of bytes read from the source so far, consumed or buffered.
+    fn read_total(&self) -> u64 {
+        self.reader.consumed_total() + self.reader.data().len() as u64
+    }
+
     /// Reads stream headers and global information from a data source.
+    ///
+    /// Fails with an `UnexpectedEof` I/O error if the source ends
+    /// before the headers are complete.
     pub fn read_headers(&mut self) -> Result<()> {
+        let mut last_total = None;
         loop {
-            // TODO: wrap fill_buf() with a check for Eof
             self.reader.fill_buf()?;
+            let total = self.read_total();
+            if last_total.is_some_and(|last| total <= last) {
+                return Err(Error::Io(io::ErrorKind::UnexpectedEof.into()));
+            }
             match self.read_headers_internal() {
                 Err(e) => match e {
                     Error::MoreDataNeeded(needed) => {
                         self.reader.grow(needed);
+                        last_total = Some(self.read_total());
                     }
                     _ => return Err(e),
                 },
@@ -132,6 +229,38 @@ impl<D: Demuxer, R: Buffered> Context<D, R> {
                             pkt.t.timebase = Some(st.timebase);
                         }
                     }
+                    if let Some(st) = self
+                        .info
+                        .streams
+                        .iter_mut()
+                        .find(|s| s.index as isize == pkt.stream_index)
+                    {
+                        st.observed_stats.update(pkt.data.len());
+                    }
+                    if !self.started_streams.contains(&pkt.stream_index) {
+                        if let Some(st) = self
+                            .info
+                            .streams
+                            .iter_mut()
+                            .find(|s| s.index as isize == pkt.stream_index)
+                        {
+                            self.started_streams.insert(pkt.stream_index);
+                            if st.params.extradata.is_none() {
+                                st.params.extradata = self.demuxer.extract_extradata(st.index, pkt);
+                            }
+                        }
+                    }
+                    if let Some(frame_rate) = self.frame_rate {
+                        if pkt.t.pts.is_none() {
+                            if let Some(prev) = self.last_times.get(&pkt.stream_index) {
+                                pkt.t.pts =
+                                    TimeInfo::interpolate(prev, frame_rate).and_then(|t| t.pts);
+                            }
+                        }
+                        if pkt.t.pts.is_some() {
+                            self.last_times.insert(pkt.stream_index, pkt.t.clone());
+                        }
+                    }
                 }
                 Ok(event)
             }
@@ -139,32 +268,49 @@ impl<D: Demuxer, R: Buffered> Context<D, R> {
     }
 
     /// Reads an event from a data source.
+    ///
+    /// `Event::Continue` events are skipped, up to `MAX_CONTINUE_EVENTS`
+    /// in a row before giving up with `Error::InvalidData`.
     pub fn read_event(&mut self) -> Result<Event> {
         // TODO: guard against infiniloops and maybe factor the loop.
+        let mut continues = 0;
         loop {
+            let consumed = self.reader.consumed_total();
             match self.read_event_internal() {
                 Err(e) => match e {
                     Error::MoreDataNeeded(needed) => {
                         let len = self.reader.data().len();
 
-                        // we might have sent MoreDatNeeded(0) to request a new call
-                        if len >= needed {
+                        // we might have sent MoreDatNeeded(0) to request a new call,
+                        // unless nothing was consumed, e.g. on an io read at the end
+                        if len >= needed && self.reader.consumed_total() != consumed {
                             continue;
                         }
-                        self.reader.grow(needed);
+                        let total = self.read_total();
+                        self.reader.grow(needed.max(1));
                         self.reader.fill_buf()?;
-                        if self.reader.data().len() <= len {
+                        if self.read_total() <= total {
                             return Ok(Event::Eof);
                         }
                     }
                     _ => return Err(e),
                 },
+                Ok(Event::Continue) => {
+                    continues += 1;
+                    if continues > MAX_CONTINUE_EVENTS {
+                        return Err(Error::InvalidData);
+                    }
+                }
                 Ok(ev) => return Ok(ev),
             }
         }
     }
 }
 
+/// Maximum number of consecutive `Event::Continue` skipped by
+/// `Context::read_event`.
+pub const MAX_CONTINUE_EVENTS: usize = 1024;
+
 /// Format descriptor.
 ///
 /// Contains information on a format and its own demuxer.
@@ -195,11 +341,18 @@ pub trait Descriptor {
     /// Returns a score which represents how much the input data are associated
     /// to a format.
     fn probe(&self, data: &[u8]) -> u8;
+    /// Returns the bit order of the format bit-packed headers, if any.
+    fn bit_order(&self) -> Option<CodebookMode> {
+        None
+    }
 }
 
 /// Maximum data size to probe a format.
 pub const PROBE_DATA: usize = 4 * 1024;
 
+/// Initial data size used by `Probe::probe_incremental`.
+pub const PROBE_DATA_INITIAL: usize = 16;
+
 /// Data whose probe score is equal or greater than the value of this constant
 /// surely is associated to the format currently being analyzed.
 pub const PROBE_SCORE_EXTENSION: u8 = 50;
@@ -208,6 +361,52 @@ pub const PROBE_SCORE_EXTENSION: u8 = 50;
 pub trait Probe<T: Descriptor + ?Sized> {
     /// Probes whether the input data is associated to a determined format.
     fn probe(&self, data: &[u8]) -> Option<&'static T>;
+
+    /// Probes the data available from a reader, starting from
+    /// `PROBE_DATA_INITIAL` bytes and reading more, up to `PROBE_DATA`,
+    /// until a format is detected.
+    ///
+    /// The data is not consumed, so the reader can be passed to the demuxer.
+    fn probe_incremental(&self, reader: &mut dyn Buffered) -> Option<&'static T> {
+        let mut window = PROBE_DATA_INITIAL;
+
+        loop {
+            let mut eof = false;
+            while !eof && reader.data().len() < window {
+                let len = reader.data().len();
+                reader.grow(window - len);
+                eof = reader.fill_buf().is_err() || reader.data().len() == len;
+            }
+
+            let data = reader.data();
+            if let Some(desc) = self.probe(&data[..data.len().min(window)]) {
+                return Some(desc);
+            }
+            if eof || window >= PROBE_DATA {
+                return None;
+            }
+            window = (window * 4).min(PROBE_DATA);
+        }
+    }
+
+    /// Probes several independent data samples and returns the format
+    /// found with the highest score along with the score.
+    ///
+    /// On equal scores the earliest sample wins.
+    fn probe_best_of(&self, samples: &[&[u8]]) -> Option<(&'static T, u8)> {
+        let mut best: Option<(&'static T, u8)> = None;
+
+        for data in samples {
+            if let Some(desc) = self.probe(data) {
+                let score = desc.probe(data);
+                if best.is_none_or(|(_, max)| score > max) {
+                    best = Some((desc, score));
+                }
+            }
+        }
+
+        best
+    }
 }
 
 impl<T: Descriptor + ?Sized> Probe<T> for [&'static T] {
@@ -231,10 +430,41 @@ impl<T: Descriptor + ?Sized> Probe<T> for [&'static T] {
     }
 }
 
+/// A list of demuxer format descriptors.
+pub struct Formats<T: 'static + Descriptor + ?Sized> {
+    list: Vec<&'static T>,
+}
+
+impl<T: Descriptor + ?Sized> FormatList for Formats<T> {
+    type D = T;
+
+    fn new() -> Self {
+        Self { list: Vec::new() }
+    }
+
+    fn by_name(&self, name: &str) -> Option<&'static Self::D> {
+        self.list
+            .iter()
+            .find(|d| d.describe().name == name)
+            .copied()
+    }
+
+    fn append(&mut self, desc: &'static Self::D) {
+        self.list.push(desc);
+    }
+}
+
+impl<T: Descriptor + ?Sized> Probe<T> for Formats<T> {
+    fn probe(&self, data: &[u8]) -> Option<&'static T> {
+        self.list.as_slice().probe(data)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::data::packet::Packet;
+    use crate::data::params::CodecParams;
     use std::io::SeekFrom;
 
     struct DummyDes {
@@ -307,9 +537,398 @@ mod test {
         demuxers.probe(b"dummy").unwrap();
     }
 
+    const DUMMY_ALT_DES: &dyn Descriptor<OutputDemuxer = DummyDemuxer> = &DummyDes {
+        d: Descr {
+            name: "dummy-alt",
+            demuxer: "dummy",
+            description: "Dummy dem with another extension",
+            extensions: &["alt"],
+            mime: &["application/dummy"],
+        },
+    };
+
+    struct BitPackedDes {
+        d: Descr,
+    }
+
+    impl Descriptor for BitPackedDes {
+        type OutputDemuxer = DummyDemuxer;
+
+        fn create(&self) -> Self::OutputDemuxer {
+            DummyDemuxer {}
+        }
+        fn describe(&self) -> &Descr {
+            &self.d
+        }
+        fn probe(&self, _data: &[u8]) -> u8 {
+            0
+        }
+        fn bit_order(&self) -> Option<CodebookMode> {
+            Some(CodebookMode::MSB)
+        }
+    }
+
+    struct LateMagicDes {
+        d: Descr,
+    }
+
+    impl Descriptor for LateMagicDes {
+        type OutputDemuxer = DummyDemuxer;
+
+        fn create(&self) -> Self::OutputDemuxer {
+            DummyDemuxer {}
+        }
+        fn describe(&self) -> &Descr {
+            &self.d
+        }
+        fn probe(&self, data: &[u8]) -> u8 {
+            match data.get(40..45) {
+                Some(b"magic") => 100,
+                _ => 0,
+            }
+        }
+    }
+
+    const LATE_MAGIC_DES: &LateMagicDes = &LateMagicDes {
+        d: Descr {
+            name: "late",
+            demuxer: "late",
+            description: "Dummy dem with a magic far from the start",
+            extensions: &[],
+            mime: &[],
+        },
+    };
+
+    #[test]
+    fn probe_incremental() {
+        let mut data = vec![0u8; 100];
+        data[40..45].copy_from_slice(b"magic");
+        let demuxers = [LATE_MAGIC_DES];
+
+        let mut reader = AccReader::with_capacity(4, Cursor::new(data.clone()));
+        let des = demuxers[..].probe_incremental(&mut reader).unwrap();
+        assert_eq!(des.describe().name, "late");
+        assert!(reader.data().len() > PROBE_DATA_INITIAL);
+        assert_eq!(reader.data()[..8], data[..8]);
+
+        let mut reader = AccReader::with_capacity(4, Cursor::new(&data[..44]));
+        assert!(demuxers[..].probe_incremental(&mut reader).is_none());
+    }
+
+    #[test]
+    fn from_buffered() {
+        let mut reader = AccReader::with_capacity(16, Cursor::new(b"0123456789"));
+        std::io::BufRead::fill_buf(&mut reader).unwrap();
+
+        let t = TimeInfo {
+            pts: Some(10),
+            dts: Some(9),
+            ..TimeInfo::default()
+        };
+        let pkt = packet_from_buffered(&reader, 4, 1, t).unwrap();
+        assert_eq!(pkt.data, b"0123");
+        assert_eq!(pkt.stream_index, 1);
+        assert_eq!((pkt.t.pts, pkt.t.dts), (Some(10), Some(9)));
+        assert_eq!(reader.data(), b"0123456789");
+
+        assert!(matches!(
+            packet_from_buffered(&reader, 20, 0, TimeInfo::default()),
+            Err(Error::MoreDataNeeded(10))
+        ));
+    }
+
+    #[test]
+    fn probe_best_of() {
+        let demuxers: &[&'static dyn Descriptor<OutputDemuxer = DummyDemuxer>] =
+            &[DUMMY_DES, LATE_MAGIC_DES];
+        let mut late = vec![0u8; 64];
+        late[40..45].copy_from_slice(b"magic");
+
+        let (des, score) = demuxers.probe_best_of(&[b"unknown", &late]).unwrap();
+        assert_eq!((des.describe().name, score), ("late", 100));
+
+        let (des, _) = demuxers.probe_best_of(&[b"dummy", &late]).unwrap();
+        assert_eq!(des.describe().name, "dummy");
+
+        assert!(demuxers.probe_best_of(&[b"unknown"]).is_none());
+        assert!(demuxers.probe_best_of(&[]).is_none());
+    }
+
+    #[test]
+    fn bit_order() {
+        use av_bitstream::bitread::AnyBitReader;
+
+        let des = BitPackedDes {
+            d: Descr {
+                name: "bitpacked",
+                demuxer: "bitpacked",
+                description: "Dummy dem with bit-packed headers",
+                extensions: &[],
+                mime: &[],
+            },
+        };
+        assert!(DUMMY_DES.bit_order().is_none());
+
+        let data = [0u8; 16];
+        let reader = AnyBitReader::new(&data, des.bit_order().unwrap());
+        assert!(matches!(reader, AnyBitReader::BE(_)));
+    }
+
+    #[test]
+    fn probe_path() {
+        use crate::common::probe_path;
+        use std::path::Path;
+
+        let demuxers = &[DUMMY_DES, DUMMY_ALT_DES];
+
+        let des = probe_path(Path::new("file.alt"), b"dummy", demuxers).unwrap();
+        assert_eq!(des.describe().name, "dummy");
+
+        let des = probe_path(Path::new("file.ALT"), b"unknown", demuxers).unwrap();
+        assert_eq!(des.describe().name, "dummy-alt");
+        let des = probe_path(Path::new("dir/file.dum"), b"unknown", demuxers).unwrap();
+        assert_eq!(des.describe().name, "dummy");
+
+        assert!(probe_path(Path::new("file"), b"unknown", demuxers).is_none());
+        assert!(probe_path(Path::new("file.mkv"), b"unknown", demuxers).is_none());
+    }
+
+    #[test]
+    fn formats() {
+        let formats = Formats::from_list(&[DUMMY_DES]);
+
+        formats.by_name("dummy").unwrap();
+        formats.probe(b"dummy").unwrap();
+        assert!(formats.by_name("other").is_none());
+    }
+
     use crate::buffer::*;
     use std::io::Cursor;
 
+    // Reports a stream for each `s` and a two bytes packet otherwise,
+    // the stream is declared in the headers unless `late_stream` is set.
+    struct ExtraDemuxer {
+        late_stream: bool,
+    }
+
+    impl Demuxer for ExtraDemuxer {
+        fn read_headers(
+            &mut self,
+            _buf: &mut dyn Buffered,
+            info: &mut GlobalInfo,
+        ) -> Result<SeekFrom> {
+            let params = CodecParams {
+                kind: None,
+                codec_id: None,
+                extradata: None,
+                bit_rate: 0,
+                convergence_window: 0,
+                delay: 0,
+            };
+            if !self.late_stream {
+                info.add_stream(Stream::from_params(&params, Rational64::new(1, 1000)));
+            }
+            Ok(SeekFrom::Current(0))
+        }
+        fn read_event(&mut self, buf: &mut dyn Buffered) -> Result<(SeekFrom, Event)> {
+            let len = buf.data().len();
+            if buf.data().first() == Some(&b's') {
+                let st = Stream::from_params(
+                    &CodecParams {
+                        kind: None,
+                        codec_id: None,
+                        extradata: None,
+                        bit_rate: 0,
+                        convergence_window: 0,
+                        delay: 0,
+                    },
+                    Rational64::new(1, 1000),
+                );
+                Ok((SeekFrom::Current(1), Event::NewStream(st)))
+            } else if 2 > len {
+                Err(Error::MoreDataNeeded(2))
+            } else {
+                let mut pkt = Packet::new();
+                pkt.stream_index = 0;
+                pkt.data = buf.data()[..2].to_vec();
+                Ok((SeekFrom::Current(2), Event::NewPacket(pkt)))
+            }
+        }
+        fn extract_extradata(&mut self, _stream_index: usize, pkt: &Packet) -> Option<Vec<u8>> {
+            Some(pkt.data.clone())
+        }
+    }
+
+    #[test]
+    fn extract_extradata() {
+        let r = AccReader::with_capacity(4, Cursor::new(b"x1x2"));
+        let mut c = Context::new(ExtraDemuxer { late_stream: false }, r);
+
+        c.read_headers().unwrap();
+        assert_eq!(c.info.streams[0].get_extradata(), None);
+
+        c.read_event().unwrap();
+        c.read_event().unwrap();
+        assert_eq!(c.info.streams[0].get_extradata(), Some(b"x1".as_slice()));
+        assert_eq!(c.info.streams[0].observed_stats.count, 2);
+        assert_eq!(c.info.streams[0].avg_packet_size(), Some(2.0));
+
+        let r = AccReader::with_capacity(4, Cursor::new(b"x1sx2"));
+        let mut c = Context::new(ExtraDemuxer { late_stream: true }, r);
+
+        c.read_headers().unwrap();
+        c.read_event().unwrap();
+        c.read_event().unwrap();
+        assert_eq!(c.info.streams[0].get_extradata(), None);
+        c.read_event().unwrap();
+        assert_eq!(c.info.streams[0].get_extradata(), Some(b"x2".as_slice()));
+    }
+
+    // Reads 4-byte packets with read_exact, relying on the conversion
+    // of its UnexpectedEof error at the end of the source.
+    struct ReadExactDemuxer {}
+
+    impl Demuxer for ReadExactDemuxer {
+        fn read_headers(
+            &mut self,
+            _buf: &mut dyn Buffered,
+            _info: &mut GlobalInfo,
+        ) -> Result<SeekFrom> {
+            Ok(SeekFrom::Current(0))
+        }
+        fn read_event(&mut self, buf: &mut dyn Buffered) -> Result<(SeekFrom, Event)> {
+            let mut pkt = Packet::zeroed(4);
+            buf.read_exact(&mut pkt.data)?;
+            Ok((SeekFrom::Current(0), Event::NewPacket(pkt)))
+        }
+    }
+
+    #[test]
+    fn read_exact_eof() {
+        let r = AccReader::with_capacity(4, Cursor::new(b"abcdef"));
+        let mut c = Context::new(ReadExactDemuxer {}, r);
+
+        c.read_headers().unwrap();
+        match c.read_event() {
+            Ok(Event::NewPacket(pkt)) => assert_eq!(pkt.data, b"abcd"),
+            ev => panic!("unexpected event {ev:?}"),
+        }
+        assert!(matches!(c.read_event(), Ok(Event::Eof)));
+        assert!(matches!(c.read_event(), Ok(Event::Eof)));
+    }
+
+    #[test]
+    fn read_headers_eof() {
+        let r = AccReader::with_capacity(4, Cursor::new(b"dummy"));
+        let mut c = Context::new(DUMMY_DES.create(), r);
+
+        match c.read_headers() {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+            res => panic!("unexpected result {res:?}"),
+        }
+    }
+
+    struct ContinueDemuxer {}
+
+    impl Demuxer for ContinueDemuxer {
+        fn read_headers(
+            &mut self,
+            _buf: &mut dyn Buffered,
+            _info: &mut GlobalInfo,
+        ) -> Result<SeekFrom> {
+            Ok(SeekFrom::Current(0))
+        }
+        fn read_event(&mut self, buf: &mut dyn Buffered) -> Result<(SeekFrom, Event)> {
+            match buf.data().first() {
+                None => Err(Error::MoreDataNeeded(1)),
+                Some(b'c') => Ok((SeekFrom::Current(1), Event::Continue)),
+                Some(_) => Ok((SeekFrom::Current(1), Event::NewPacket(Packet::new()))),
+            }
+        }
+    }
+
+    #[test]
+    fn skip_continue() {
+        let r = AccReader::with_capacity(4, Cursor::new(b"cccccp"));
+        let mut c = Context::new(ContinueDemuxer {}, r);
+
+        c.read_headers().unwrap();
+        assert!(matches!(c.read_event(), Ok(Event::NewPacket(_))));
+        assert!(matches!(c.read_event(), Ok(Event::Eof)));
+
+        let buf = vec![b'c'; MAX_CONTINUE_EVENTS + 1];
+        let r = AccReader::with_capacity(4, Cursor::new(buf));
+        let mut c = Context::new(ContinueDemuxer {}, r);
+
+        c.read_headers().unwrap();
+        assert!(matches!(c.read_event(), Err(Error::InvalidData)));
+    }
+
+    // Declares the number of streams in its first byte, then
+    // reports a stream for each `s` and a packet for each `p`.
+    struct StreamsDemuxer {
+        declared: Option<usize>,
+    }
+
+    impl Demuxer for StreamsDemuxer {
+        fn read_headers(
+            &mut self,
+            buf: &mut dyn Buffered,
+            _info: &mut GlobalInfo,
+        ) -> Result<SeekFrom> {
+            match buf.data().first() {
+                None => Err(Error::MoreDataNeeded(1)),
+                Some(&n) => {
+                    self.declared = (n as char).to_digit(10).map(|n| n as usize);
+                    Ok(SeekFrom::Current(1))
+                }
+            }
+        }
+        fn read_event(&mut self, buf: &mut dyn Buffered) -> Result<(SeekFrom, Event)> {
+            let params = CodecParams {
+                kind: None,
+                codec_id: None,
+                extradata: None,
+                bit_rate: 0,
+                convergence_window: 0,
+                delay: 0,
+            };
+            match buf.data().first() {
+                None => Err(Error::MoreDataNeeded(1)),
+                Some(b's') => {
+                    let st = Stream::from_params(&params, Rational64::new(1, 1000));
+                    Ok((SeekFrom::Current(1), Event::NewStream(st)))
+                }
+                Some(_) => Ok((SeekFrom::Current(1), Event::NewPacket(Packet::new()))),
+            }
+        }
+        fn expected_stream_count(&self) -> Option<usize> {
+            self.declared
+        }
+    }
+
+    #[test]
+    fn streams_complete() {
+        let r = AccReader::with_capacity(4, Cursor::new(b"2sps"));
+        let mut c = Context::new(StreamsDemuxer { declared: None }, r);
+
+        c.read_headers().unwrap();
+        assert!(!c.streams_complete());
+        c.read_event().unwrap();
+        c.read_event().unwrap();
+        assert!(!c.streams_complete());
+        c.read_event().unwrap();
+        assert!(c.streams_complete());
+
+        let r = AccReader::with_capacity(4, Cursor::new(b"?ss"));
+        let mut c = Context::new(StreamsDemuxer { declared: None }, r);
+
+        c.read_headers().unwrap();
+        c.read_event().unwrap();
+        c.read_event().unwrap();
+        assert!(!c.streams_complete());
+    }
+
     #[test]
     fn read_headers() {
         let buf = b"dummy header";
@@ -320,6 +939,19 @@ mod test {
         c.read_headers().unwrap();
     }
 
+    #[test]
+    fn positions() {
+        let buf = b"dummy header p1 e1 p1 ";
+        let r = AccReader::with_capacity(16, Cursor::new(buf));
+        let d = DUMMY_DES.create();
+        let mut c = Context::new(d, r);
+
+        c.read_headers().unwrap();
+
+        assert_eq!(c.consumed_total(), 9);
+        assert_eq!(c.inner_position().unwrap(), 16);
+    }
+
     #[test]
     fn read_event() {
         let buf = b"dummy header p1 e1 p1 ";
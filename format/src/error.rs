@@ -33,7 +33,10 @@ impl fmt::Display for Error {
 
 impl From<io::Error> for Error {
     fn from(value: io::Error) -> Self {
-        Self::Io(value)
+        match value.kind() {
+            io::ErrorKind::UnexpectedEof => Self::MoreDataNeeded(0),
+            _ => Self::Io(value),
+        }
     }
 }
 
@@ -46,7 +49,7 @@ mod tests {
 
     #[test]
     fn io_error_conversion() {
-        let io_err = io::Error::new(io::ErrorKind::Other, "foobar");
+        let io_err = io::Error::other("foobar");
 
         let err: Error = io_err.into();
 
@@ -55,4 +58,16 @@ mod tests {
             _ => panic!("Error doesn't match"),
         }
     }
+
+    #[test]
+    fn eof_error_conversion() {
+        let io_err = io::Error::from(io::ErrorKind::UnexpectedEof);
+
+        let err: Error = io_err.into();
+
+        match err {
+            Error::MoreDataNeeded(0) => {}
+            _ => panic!("Error doesn't match"),
+        }
+    }
 }
@@ -2,16 +2,20 @@ use crate::common::*;
 use crate::data::packet::Packet;
 use crate::data::value::*;
 use std::any::Any;
-use std::io::{Cursor, Seek, SeekFrom, Write};
+use std::io::{self, Cursor, Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::sync::Arc;
 
 use crate::error::*;
 
+use av_bitstream::crc::Crc;
+
 /// Runtime wrapper around a [`Write`] trait object
 /// which optionally supports [`Seek`] functionality.
 pub struct Writer<W = Cursor<Vec<u8>>> {
     writer: W,
     bytes_written: usize,
+    total_written: usize,
 }
 
 impl<W: Write> Writer<W> {
@@ -20,6 +24,7 @@ impl<W: Write> Writer<W> {
         Self {
             writer: inner,
             bytes_written: 0,
+            total_written: 0,
         }
     }
 }
@@ -30,10 +35,80 @@ impl<W: Write> Writer<W> {
         self.bytes_written
     }
 
+    /// Returns the number of bytes written so far, regardless of
+    /// any seek.
+    pub fn total_written(&self) -> usize {
+        self.total_written
+    }
+
     /// Returns a reference to the underlying writer and bytes written.
     pub fn as_ref(&self) -> (&W, usize) {
         (&self.writer, self.bytes_written)
     }
+
+    /// Writes `fill` bytes until the stream position is a multiple
+    /// of `alignment`.
+    pub fn pad_to(&mut self, alignment: usize, fill: u8) -> Result<()> {
+        if alignment == 0 {
+            return Ok(());
+        }
+
+        let padding = (alignment - self.bytes_written % alignment) % alignment;
+        self.write_all(&vec![fill; padding])?;
+
+        Ok(())
+    }
+}
+
+impl<W: Write + 'static> Writer<W> {
+    /// Feeds the bytes written in `range` into `crc`.
+    ///
+    /// Only writers backed by an in-memory buffer (`Vec<u8>` or
+    /// `Cursor<Vec<u8>>`) keep the written data around, any other
+    /// writer returns an error.
+    pub fn crc_over(&self, range: Range<usize>, crc: &mut Crc) -> Result<()> {
+        let inner = &self.writer as &dyn Any;
+        let buf = if let Some(buf) = inner.downcast_ref::<Vec<u8>>() {
+            buf
+        } else if let Some(cur) = inner.downcast_ref::<Cursor<Vec<u8>>>() {
+            cur.get_ref()
+        } else {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "writer is not buffer-backed",
+            )));
+        };
+
+        crc.update(buf.get(range).ok_or(Error::InvalidData)?);
+
+        Ok(())
+    }
+}
+
+impl<W: Write + Reserve> Writer<W> {
+    /// Reserves capacity for at least `additional` more bytes
+    /// in the underlying buffer.
+    pub fn reserve(&mut self, additional: usize) {
+        self.writer.reserve(additional);
+    }
+}
+
+/// Used by writers backed by a growable in-memory buffer.
+pub trait Reserve {
+    /// Reserves capacity for at least `additional` more bytes.
+    fn reserve(&mut self, additional: usize);
+}
+
+impl Reserve for Vec<u8> {
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+}
+
+impl Reserve for Cursor<Vec<u8>> {
+    fn reserve(&mut self, additional: usize) {
+        self.get_mut().reserve(additional);
+    }
 }
 
 impl<W: Write> Write for Writer<W> {
@@ -42,6 +117,7 @@ impl<W: Write> Write for Writer<W> {
 
         if let Ok(written) = result {
             self.bytes_written += written;
+            self.total_written += written;
         }
 
         result
@@ -84,6 +160,22 @@ pub trait Muxer: Send {
     /// This method should be called as many times as the number of options
     /// present in a muxer.
     fn set_option(&mut self, key: &str, val: Value) -> Result<()>;
+
+    /// Lists the options accepted by a muxer.
+    fn options(&self) -> &'static [OptionDescr] {
+        &[]
+    }
+}
+
+/// Description of an option accepted by a muxer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OptionDescr {
+    /// Option name, as passed to `set_option`.
+    pub name: &'static str,
+    /// Kind of value the option expects.
+    pub value_kind: ValueKind,
+    /// Whether the option must be set before writing the header.
+    pub required: bool,
 }
 
 /// Auxiliary structure to encapsulate a muxer object and
@@ -114,23 +206,30 @@ impl<M: Muxer, W: Write> Context<M, W> {
 
     /// Writes a stream header to an internal buffer and returns how many
     /// bytes were written or an error.
-    pub fn write_header(&mut self) -> Result<()> {
-        self.muxer.write_header(&mut self.writer)
+    pub fn write_header(&mut self) -> Result<usize> {
+        let start = self.writer.total_written();
+        self.muxer.write_header(&mut self.writer)?;
+
+        Ok(self.writer.total_written() - start)
     }
 
     /// Writes a stream packet to an internal buffer and returns how many
     /// bytes were written or an error.
-    pub fn write_packet(&mut self, pkt: Arc<Packet>) -> Result<()> {
-        self.muxer.write_packet(&mut self.writer, pkt)
+    pub fn write_packet(&mut self, pkt: Arc<Packet>) -> Result<usize> {
+        let start = self.writer.total_written();
+        self.muxer.write_packet(&mut self.writer, pkt)?;
+
+        Ok(self.writer.total_written() - start)
     }
 
     /// Writes a stream trailer to an internal buffer and returns how many
     /// bytes were written or an error.
-    pub fn write_trailer(&mut self) -> Result<()> {
+    pub fn write_trailer(&mut self) -> Result<usize> {
+        let start = self.writer.total_written();
         self.muxer.write_trailer(&mut self.writer)?;
         self.writer.flush()?;
 
-        Ok(())
+        Ok(self.writer.total_written() - start)
     }
 
     /// Sets global media file information for a muxer.
@@ -149,6 +248,11 @@ impl<M: Muxer, W: Write> Context<M, W> {
         self.muxer.set_option(key, val.into())
     }
 
+    /// Lists the options accepted by the muxer.
+    pub fn options(&self) -> &'static [OptionDescr] {
+        self.muxer.options()
+    }
+
     /// Returns the underlying writer.
     pub fn writer(&self) -> &Writer<W> {
         &self.writer
@@ -160,6 +264,16 @@ impl<M: Muxer, W: Write> Context<M, W> {
     }
 }
 
+impl<M: Muxer, W: Write + Reserve> Context<M, W> {
+    /// Reserves output capacity up front from an estimated size in bytes.
+    ///
+    /// Useful to avoid repeated reallocations when muxing large files
+    /// into an in-memory buffer.
+    pub fn reserve_output(&mut self, bytes: usize) {
+        self.writer.reserve(bytes);
+    }
+}
+
 /// Format descriptor.
 ///
 /// Contains information on a format and its own muxer.
@@ -200,6 +314,27 @@ impl<T: Descriptor + ?Sized> Lookup<T> for [&'static T] {
     }
 }
 
+/// A list of muxer format descriptors.
+pub struct Formats<T: 'static + Descriptor + ?Sized> {
+    list: Vec<&'static T>,
+}
+
+impl<T: Descriptor + ?Sized> FormatList for Formats<T> {
+    type D = T;
+
+    fn new() -> Self {
+        Self { list: Vec::new() }
+    }
+
+    fn by_name(&self, name: &str) -> Option<&'static Self::D> {
+        self.list.as_slice().by_name(name)
+    }
+
+    fn append(&mut self, desc: &'static Self::D) {
+        self.list.push(desc);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -228,18 +363,18 @@ mod test {
 
         fn write_header<W: Write>(&mut self, out: &mut Writer<W>) -> Result<()> {
             let buf = b"Dummy header";
-            out.write_all(buf.as_slice()).unwrap();
+            out.write_all(buf.as_slice())?;
             Ok(())
         }
 
         fn write_packet<W: Write>(&mut self, out: &mut Writer<W>, pkt: Arc<Packet>) -> Result<()> {
-            out.write_all(&pkt.data).unwrap();
+            out.write_all(&pkt.data)?;
             Ok(())
         }
 
         fn write_trailer<W: Write>(&mut self, out: &mut Writer<W>) -> Result<()> {
             let buf = b"Dummy trailer";
-            out.write_all(buf.as_slice()).unwrap();
+            out.write_all(buf.as_slice())?;
             Ok(())
         }
 
@@ -250,6 +385,14 @@ mod test {
         fn set_option(&mut self, _key: &str, _val: Value) -> Result<()> {
             Ok(())
         }
+
+        fn options(&self) -> &'static [OptionDescr] {
+            &[OptionDescr {
+                name: "title",
+                value_kind: ValueKind::Str,
+                required: true,
+            }]
+        }
     }
 
     impl Descriptor for DummyDes {
@@ -280,21 +423,41 @@ mod test {
         muxers.by_name("dummy").unwrap();
     }
 
+    #[test]
+    fn options() {
+        let muxer = Context::new(DummyMuxer::new(), Writer::new(Vec::new()));
+
+        let opts = muxer.options();
+        assert_eq!(opts.len(), 1);
+        assert_eq!(opts[0].name, "title");
+        assert_eq!(opts[0].value_kind, ValueKind::Str);
+        assert!(opts[0].required);
+    }
+
+    #[test]
+    fn formats() {
+        let formats = Formats::from_list(&[DUMMY_DES]);
+
+        formats.by_name("dummy").unwrap();
+        assert!(formats.by_name("other").is_none());
+    }
+
     fn run_muxer<W: Write>(writer: Writer<W>) -> Context<DummyMuxer, W> {
         let mux = DummyMuxer::new();
 
         let mut muxer = Context::new(mux, writer);
 
         muxer.configure().unwrap();
-        muxer.write_header().unwrap();
+        assert_eq!(muxer.write_header().unwrap(), b"Dummy header".len());
 
         // Write zeroed packets of a certain size
         for _ in 0..DUMMY_PACKETS_NUMBER {
             let packet = Packet::zeroed(DUMMY_PACKET_LENGTH);
-            muxer.write_packet(Arc::new(packet)).unwrap();
+            let written = muxer.write_packet(Arc::new(packet)).unwrap();
+            assert_eq!(written, DUMMY_PACKET_LENGTH);
         }
 
-        muxer.write_trailer().unwrap();
+        assert_eq!(muxer.write_trailer().unwrap(), b"Dummy trailer".len());
         muxer
     }
 
@@ -337,6 +500,42 @@ mod test {
         );
     }
 
+    #[test]
+    fn reserve_output() {
+        let mux = DummyMuxer::new();
+        let mut muxer = Context::new(mux, Writer::new(Vec::new()));
+
+        muxer.reserve_output(1024);
+        assert!(muxer.writer().as_ref().0.capacity() >= 1024);
+        assert_eq!(muxer.writer().as_ref().1, 0);
+    }
+
+    #[test]
+    fn pad_to() {
+        let mut writer = Writer::new(Vec::new());
+
+        writer.write_all(&[1, 2, 3, 4, 5]).unwrap();
+        writer.pad_to(8, 0xFF).unwrap();
+        assert_eq!(writer.as_ref(), (&vec![1, 2, 3, 4, 5, 0xFF, 0xFF, 0xFF], 8));
+
+        writer.pad_to(8, 0xFF).unwrap();
+        assert_eq!(writer.position(), 8);
+    }
+
+    #[test]
+    fn crc_over() {
+        let mut writer = Writer::new(Cursor::new(Vec::new()));
+        writer.write_all(b"head123456789").unwrap();
+
+        let mut crc = Crc::crc32_mpeg();
+        writer.crc_over(4..13, &mut crc).unwrap();
+        assert_eq!(crc.sum(), 0x0376_e6e7);
+        assert!(writer.crc_over(4..14, &mut crc).is_err());
+
+        let writer = Writer::new(FailingWriter);
+        assert!(writer.crc_over(0..0, &mut crc).is_err());
+    }
+
     #[test]
     fn stdout_muxer() {
         use std::io::stdout;
@@ -360,6 +559,37 @@ mod test {
         let mut writer = muxer.into_writer();
         writer.seek(SeekFrom::Start(3)).unwrap();
         assert!(writer.bytes_written == 3);
+        writer.write_all(b"xy").unwrap();
+        assert_eq!(writer.position(), 5);
+        assert_eq!(
+            writer.total_written(),
+            DUMMY_HEADER_LENGTH
+                + (DUMMY_PACKETS_NUMBER * DUMMY_PACKET_LENGTH)
+                + DUMMY_TRAILER_LENGTH
+                + 2
+        );
         assert!(writer.as_ref().0.metadata().unwrap().len() != 0);
     }
+
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _bytes: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("write failed"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_error_propagation() {
+        let mut muxer = Context::new(DummyMuxer::new(), Writer::new(FailingWriter));
+
+        match muxer.write_header() {
+            Err(Error::Io(_)) => {}
+            _ => panic!("Error doesn't match"),
+        }
+    }
 }
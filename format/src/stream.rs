@@ -3,6 +3,34 @@ use crate::rational::Rational64;
 use std::any::Any;
 use std::sync::Arc;
 
+/// Packet size statistics observed while demuxing a stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PacketStats {
+    /// Number of packets.
+    pub count: u64,
+    /// Total size of the packets in bytes.
+    pub total_bytes: u64,
+    /// Size of the smallest packet.
+    pub min_size: usize,
+    /// Size of the largest packet.
+    pub max_size: usize,
+}
+
+impl PacketStats {
+    /// Accounts for a new packet of the given size.
+    pub fn update(&mut self, size: usize) {
+        if self.count == 0 {
+            self.min_size = size;
+            self.max_size = size;
+        } else {
+            self.min_size = self.min_size.min(size);
+            self.max_size = self.max_size.max(size);
+        }
+        self.count += 1;
+        self.total_bytes += size as u64;
+    }
+}
+
 /// Stream data.
 #[derive(Debug, Clone)]
 pub struct Stream {
@@ -31,6 +59,8 @@ pub struct Stream {
     ///
     /// This data cannot be cloned.
     pub user_private: Option<Arc<dyn Any + Send + Sync>>,
+    /// Packet statistics gathered by the demuxer context.
+    pub observed_stats: PacketStats,
 }
 
 impl Stream {
@@ -44,12 +74,25 @@ impl Stream {
             duration: None,
             timebase,
             user_private: None,
+            observed_stats: PacketStats::default(),
         }
     }
     /// Returns extradata associated to the codec parameters of a stream.
     pub fn get_extradata(&self) -> Option<&[u8]> {
         self.params.extradata.as_deref()
     }
+    /// Returns the average size of the packets demuxed so far.
+    ///
+    /// If no packet has been observed, `None` is returned.
+    pub fn avg_packet_size(&self) -> Option<f64> {
+        let stats = &self.observed_stats;
+
+        if stats.count == 0 {
+            None
+        } else {
+            Some(stats.total_bytes as f64 / stats.count as f64)
+        }
+    }
 }
 
 /// Group of streams.
@@ -65,3 +108,30 @@ pub struct StreamGroup<'a> {
     /// Streams of the group.
     pub streams: &'a [Stream],
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn avg_packet_size() {
+        let params = CodecParams {
+            kind: None,
+            codec_id: None,
+            extradata: None,
+            bit_rate: 0,
+            convergence_window: 0,
+            delay: 0,
+        };
+        let mut st = Stream::from_params(&params, Rational64::new(1, 1000));
+        assert_eq!(st.avg_packet_size(), None);
+
+        for size in [100, 20, 300, 60] {
+            st.observed_stats.update(size);
+        }
+        assert_eq!(st.observed_stats.count, 4);
+        assert_eq!(st.observed_stats.min_size, 20);
+        assert_eq!(st.observed_stats.max_size, 300);
+        assert_eq!(st.avg_packet_size(), Some(120.0));
+    }
+}
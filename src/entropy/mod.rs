@@ -1 +1,250 @@
+//! Entropy coding primitives.
+//!
+//! The boolean range coder follows the VP8 specification (RFC 6386),
+//! where `prob` is the probability of a bit being zero, scaled to 256.
 
+use crate::bitstream::byteread::{get_u16b, get_u8};
+
+/// Boolean range decoder.
+pub struct RangeDecoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    value: u32,
+    range: u32,
+    bit_count: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    /// Creates a new range decoder over a buffer.
+    ///
+    /// Reading past the end of the buffer yields zero bytes.
+    pub fn new(data: &'a [u8]) -> Self {
+        let mut dec = RangeDecoder {
+            data,
+            pos: 0,
+            value: 0,
+            range: 255,
+            bit_count: 0,
+        };
+
+        if data.len() >= 2 {
+            dec.value = get_u16b(data) as u32;
+            dec.pos = 2;
+        } else {
+            dec.value = (dec.next_byte() as u32) << 8 | dec.next_byte() as u32;
+        }
+
+        dec
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        match self.data.get(self.pos..) {
+            Some(buf) if !buf.is_empty() => {
+                self.pos += 1;
+                get_u8(buf)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Reads a bit whose probability of being zero is `prob / 256`.
+    pub fn read_bool(&mut self, prob: u8) -> bool {
+        let split = 1 + (((self.range - 1) * prob as u32) >> 8);
+        let big_split = split << 8;
+
+        let bit = if self.value >= big_split {
+            self.range -= split;
+            self.value -= big_split;
+            true
+        } else {
+            self.range = split;
+            false
+        };
+
+        while self.range < 128 {
+            self.value <<= 1;
+            self.range <<= 1;
+            self.bit_count += 1;
+            if self.bit_count == 8 {
+                self.bit_count = 0;
+                self.value |= self.next_byte() as u32;
+            }
+        }
+
+        bit
+    }
+
+    /// Reads an unsigned value of `bits` equiprobable bits,
+    /// most significant bit first.
+    pub fn read_literal(&mut self, bits: usize) -> u32 {
+        (0..bits).fold(0, |v, _| (v << 1) | self.read_bool(128) as u32)
+    }
+}
+
+/// Boolean range encoder, the counterpart of `RangeDecoder`.
+pub struct RangeEncoder {
+    output: Vec<u8>,
+    bottom: u32,
+    range: u32,
+    bit_count: i32,
+}
+
+impl Default for RangeEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RangeEncoder {
+    /// Creates a new range encoder.
+    pub fn new() -> Self {
+        RangeEncoder {
+            output: Vec::new(),
+            bottom: 0,
+            range: 255,
+            bit_count: 24,
+        }
+    }
+
+    fn add_one_to_output(&mut self) {
+        for b in self.output.iter_mut().rev() {
+            if *b == 255 {
+                *b = 0;
+            } else {
+                *b += 1;
+                break;
+            }
+        }
+    }
+
+    /// Writes a bit whose probability of being zero is `prob / 256`.
+    pub fn write_bool(&mut self, bit: bool, prob: u8) {
+        let split = 1 + (((self.range - 1) * prob as u32) >> 8);
+
+        if bit {
+            self.bottom = self.bottom.wrapping_add(split);
+            self.range -= split;
+        } else {
+            self.range = split;
+        }
+
+        while self.range < 128 {
+            self.range <<= 1;
+            if self.bottom & (1 << 31) != 0 {
+                self.add_one_to_output();
+            }
+            self.bottom <<= 1;
+            self.bit_count -= 1;
+            if self.bit_count == 0 {
+                self.output.push((self.bottom >> 24) as u8);
+                self.bottom &= (1 << 24) - 1;
+                self.bit_count = 8;
+            }
+        }
+    }
+
+    /// Writes the `bits` least significant bits of `val` as equiprobable
+    /// bits, most significant bit first.
+    pub fn write_literal(&mut self, val: u32, bits: usize) {
+        for i in (0..bits).rev() {
+            self.write_bool((val >> i) & 1 != 0, 128);
+        }
+    }
+
+    /// Flushes the pending bits and returns the encoded data.
+    pub fn finish(mut self) -> Vec<u8> {
+        let c = self.bit_count;
+        let mut v = self.bottom;
+
+        if v & (1u32.wrapping_shl(32 - c as u32)) != 0 {
+            self.add_one_to_output();
+        }
+        v = v.wrapping_shl((c & 7) as u32);
+        for _ in 0..(c >> 3) {
+            v = v.wrapping_shl(8);
+        }
+        for _ in 0..4 {
+            self.output.push((v >> 24) as u8);
+            v = v.wrapping_shl(8);
+        }
+
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip_bools() {
+        let bits = (0..1000u32)
+            .map(|i| (i * 7919 % 13 < 3, (i * 31 % 255 + 1) as u8))
+            .collect::<Vec<_>>();
+
+        let mut enc = RangeEncoder::new();
+        for &(bit, prob) in bits.iter() {
+            enc.write_bool(bit, prob);
+        }
+        let data = enc.finish();
+
+        let mut dec = RangeDecoder::new(&data);
+        for &(bit, prob) in bits.iter() {
+            assert_eq!(dec.read_bool(prob), bit);
+        }
+    }
+
+    // Deterministic xorshift generator
+    fn xorshift(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    #[test]
+    fn roundtrip_random() {
+        for seed in [1u32, 0xdead_beef, 12345] {
+            let mut state = seed;
+            let bits = (0..10000)
+                .map(|_| {
+                    let prob = (xorshift(&mut state) % 255 + 1) as u8;
+                    // Zero with a probability of prob / 256
+                    let bit = (xorshift(&mut state) & 0xff) as u8 >= prob;
+                    (bit, prob)
+                })
+                .collect::<Vec<_>>();
+
+            let mut enc = RangeEncoder::new();
+            for &(bit, prob) in bits.iter() {
+                enc.write_bool(bit, prob);
+            }
+            let data = enc.finish();
+
+            let mut dec = RangeDecoder::new(&data);
+            for &(bit, prob) in bits.iter() {
+                assert_eq!(dec.read_bool(prob), bit);
+            }
+        }
+    }
+
+    #[test]
+    fn roundtrip_literals() {
+        let values = [0u32, 1, 5, 127, 255, 1000, 65535, 0x7fff_ffff];
+
+        let mut enc = RangeEncoder::new();
+        for &v in values.iter() {
+            enc.write_literal(v, 31);
+        }
+        enc.write_bool(true, 1);
+        enc.write_bool(false, 255);
+        let data = enc.finish();
+
+        let mut dec = RangeDecoder::new(&data);
+        for &v in values.iter() {
+            assert_eq!(dec.read_literal(31), v);
+        }
+        assert!(dec.read_bool(1));
+        assert!(!dec.read_bool(255));
+    }
+}
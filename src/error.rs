@@ -0,0 +1,26 @@
+//! Errors shared by the multimedia data processing modules.
+
+use std::fmt;
+
+/// Processing errors.
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid input data.
+    InvalidData,
+    /// Unsupported requested conversion.
+    Unsupported(String),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidData => write!(f, "Invalid Data"),
+            Error::Unsupported(feat) => write!(f, "Unsupported feature {feat}"),
+        }
+    }
+}
+
+/// A specialized `Result` type for processing operations.
+pub type Result<T> = ::std::result::Result<T, Error>;
@@ -2,102 +2,40 @@
 #![allow(dead_code)]
 
 use std::io::ErrorKind::*;
-use std::io::{BufRead, Error, Read, Result};
+use std::io::{BufRead, Error, Result};
 
 use crate::bitstream::byteread::*;
 
-fn get_buffer<R: Read + ?Sized>(reader: &mut R, buf: &mut [u8]) -> Result<()> {
-    let mut nread = 0usize;
-    while nread < buf.len() {
-        match reader.read(&mut buf[nread..]) {
-            Ok(0) => return Err(Error::new(UnexpectedEof, "Empty")),
-            Ok(n) => nread += n,
-            Err(e) => return Err(e),
-        }
-    }
-    Ok(())
-}
-
-macro_rules! get {
-    ($s: ident, $name: ident, $size: expr) => {{
-        let mut buf = [0; $size];
-        get_buffer($s, &mut buf)?;
-        Ok($name(&buf))
-    }};
-}
-
-pub trait ByteRead: Read {
-    fn get_u8(&mut self) -> Result<u8> {
-        get!(self, get_u8, 1)
-    }
-    fn get_i8(&mut self) -> Result<i8> {
-        get!(self, get_i8, 1)
-    }
-    fn get_u16l(&mut self) -> Result<u16> {
-        get!(self, get_u16l, 2)
-    }
-    fn get_i16l(&mut self) -> Result<i16> {
-        get!(self, get_i16l, 2)
-    }
-    fn get_u32l(&mut self) -> Result<u32> {
-        get!(self, get_u32l, 4)
-    }
-    fn get_i32l(&mut self) -> Result<i32> {
-        get!(self, get_i32l, 4)
-    }
-    fn get_u64l(&mut self) -> Result<u64> {
-        get!(self, get_u64l, 8)
-    }
-    fn get_i64l(&mut self) -> Result<i64> {
-        get!(self, get_i64l, 8)
-    }
-    fn get_f32l(&mut self) -> Result<f32> {
-        get!(self, get_f32l, 4)
-    }
-    fn get_f64l(&mut self) -> Result<f64> {
-        get!(self, get_f64l, 8)
-    }
-    fn get_u16b(&mut self) -> Result<u16> {
-        get!(self, get_u16b, 2)
-    }
-    fn get_i16b(&mut self) -> Result<i16> {
-        get!(self, get_i16b, 2)
-    }
-    fn get_u32b(&mut self) -> Result<u32> {
-        get!(self, get_u32b, 4)
-    }
-    fn get_i32b(&mut self) -> Result<i32> {
-        get!(self, get_i32b, 4)
-    }
-    fn get_u64b(&mut self) -> Result<u64> {
-        get!(self, get_u64b, 8)
-    }
-    fn get_i64b(&mut self) -> Result<i64> {
-        get!(self, get_i64b, 8)
-    }
-    fn get_f32b(&mut self) -> Result<f32> {
-        get!(self, get_f32b, 4)
-    }
-    fn get_f64b(&mut self) -> Result<f64> {
-        get!(self, get_f64b, 8)
-    }
-}
-
-impl<R: Read + ?Sized> ByteRead for R {}
-
 macro_rules! peek {
     ($s: ident, $name: ident, $size: expr) => {{
-        let buf = $s.fill_buf()?;
-        if buf.len() < $size {
-            Err(Error::new(UnexpectedEof, "Empty"))
-        } else {
-            Ok($name(&buf))
-        }
+        let buf = $s.fill_at_least($size)?;
+        Ok($name(buf))
     }};
 }
 
 // TODO: bind to a trait that is a bit more strict
 pub trait BytePeek: BufRead {
+    // Calls fill_buf until at least n bytes are buffered, failing once
+    // the buffer stops growing.
+    //
+    // Peeking across a buffer boundary needs a fill_buf that moves the
+    // pending data to the start of the buffer and reads more, as the one
+    // of AccReader does. std BufReader only refills an empty buffer, so
+    // it can only peek values that are already buffered.
+    fn fill_at_least(&mut self, n: usize) -> Result<&[u8]> {
+        let mut len = 0;
+        loop {
+            let buf_len = self.fill_buf()?.len();
+            if buf_len >= n {
+                break;
+            }
+            if buf_len == len {
+                return Err(Error::new(UnexpectedEof, "Empty"));
+            }
+            len = buf_len;
+        }
+        self.fill_buf()
+    }
     fn peek_u8(&mut self) -> Result<u8> {
         peek!(self, get_u8, 1)
     }
@@ -158,35 +96,9 @@ impl<R: BufRead + ?Sized> BytePeek for R {}
 
 #[cfg(test)]
 mod test {
+    use crate::format::buffer::AccReader;
     use crate::io::byteread::*;
-    use std::io::{BufReader, Cursor};
-
-    macro_rules! test_read {
-        {$fun: ident, $val: expr, $len: expr} => {
-            #[test]
-            fn $fun() {
-                let mut buf = Cursor::new(vec![1; 17]);
-
-                for _ in 0..$len {
-                    let v = buf.$fun().unwrap();
-                    assert!(v == $val);
-                }
-                match buf.$fun() {
-                    Ok(_) => assert!(false),
-                    Err(e) => assert!(e.kind() == UnexpectedEof)
-                }
-            }
-        }
-    }
-
-    test_read! { get_u8, 1, 17 }
-    test_read! { get_i8, 1, 17 }
-    test_read! { get_u16l, 257, 8 }
-    test_read! { get_i16l, 257, 8 }
-    test_read! { get_u32l, 16_843_009, 4 }
-    test_read! { get_i32l, 16_843_009, 4 }
-    test_read! { get_u64l, 72_340_172_838_076_673u64, 2 }
-    test_read! { get_i64l, 72_340_172_838_076_673i64, 2 }
+    use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
 
     macro_rules! test_peek {
         {$fun: ident, $val: expr, $len: expr} => {
@@ -211,4 +123,42 @@ mod test {
     test_peek! { peek_i32l, 16_843_009, 5 }
     test_peek! { peek_u64l, 72_340_172_838_076_673u64, 3 }
     test_peek! { peek_i64l, 72_340_172_838_076_673i64, 3 }
+
+    struct ByteByByte(Cursor<Vec<u8>>);
+
+    impl Read for ByteByByte {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    impl Seek for ByteByByte {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    #[test]
+    fn peek_across_fills() {
+        let cur = ByteByByte(Cursor::new(vec![1, 2, 3, 4, 5]));
+        let mut buf = AccReader::with_capacity(8, cur);
+
+        assert_eq!(buf.peek_u32b().unwrap(), 0x0102_0304);
+        assert_eq!(buf.peek_u8().unwrap(), 1);
+        assert_eq!(buf.peek_u64b().unwrap_err().kind(), UnexpectedEof);
+    }
+
+    #[test]
+    fn peek_across_boundary() {
+        let cur = Cursor::new(vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut buf = AccReader::with_capacity(4, cur);
+
+        assert_eq!(buf.peek_u32b().unwrap(), 0x0102_0304);
+        buf.consume(3);
+        assert_eq!(buf.peek_u32b().unwrap(), 0x0405_0607);
+        buf.consume(3);
+        assert_eq!(buf.peek_u16b().unwrap(), 0x0708);
+        assert_eq!(buf.peek_u32b().unwrap_err().kind(), UnexpectedEof);
+    }
 }
@@ -27,10 +27,11 @@ pub mod bitstream {
 pub use av_data::rational;
 
 // core functionalities
-mod entropy;
+pub mod entropy;
 mod io;
 
 // raw multimedia data manipulation
+pub mod error;
 mod filter;
-mod resample;
-mod scale;
+pub mod resample;
+pub mod scale;
@@ -1 +1,212 @@
+//! Audio resampling.
+//!
+//! Only 32-bit float planar audio is supported for now.
 
+use std::sync::Arc;
+
+use crate::data::audiosample::Soniton;
+use crate::data::frame::{AudioInfo, Frame, FrameBuffer, FrameBufferConv, MediaKind};
+
+pub use crate::error::{Error, Result};
+
+fn is_f32_planar(format: &Soniton) -> bool {
+    format.float && format.planar && format.bits == 32
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Linear interpolation resampler between two sample rates.
+///
+/// The channel layout is passed through unchanged.
+pub struct Resampler {
+    src: AudioInfo,
+    dst: AudioInfo,
+    // Input samples per output sample, as step / den
+    step: u64,
+    den: u64,
+    // Position of the next output sample in units of 1 / den,
+    // relative to the last sample of the previous input frame
+    pos: u64,
+    // Last sample of each channel of the previous input frame
+    history: Option<Vec<f32>>,
+}
+
+impl Resampler {
+    /// Creates a new resampler from `src` to `dst` audio.
+    ///
+    /// Both sides must use 32-bit float planar samples and have the same
+    /// number of channels.
+    pub fn new(src: AudioInfo, dst: AudioInfo) -> Result<Resampler> {
+        if !is_f32_planar(&src.format) || !is_f32_planar(&dst.format) {
+            return Err(Error::Unsupported("non f32 planar audio".to_owned()));
+        }
+        if src.map.len() != dst.map.len() {
+            return Err(Error::Unsupported("channel count conversion".to_owned()));
+        }
+        if src.sample_rate == 0 || dst.sample_rate == 0 {
+            return Err(Error::InvalidData);
+        }
+
+        let src_rate = src.sample_rate as u64;
+        let dst_rate = dst.sample_rate as u64;
+        let div = gcd(src_rate, dst_rate);
+
+        Ok(Resampler {
+            src,
+            dst,
+            step: src_rate / div,
+            den: dst_rate / div,
+            pos: dst_rate / div,
+            history: None,
+        })
+    }
+
+    /// Resamples an audio frame.
+    ///
+    /// The state is kept across calls, so consecutive frames of
+    /// a stream are resampled seamlessly.
+    pub fn process(&mut self, input: &Frame) -> Result<Frame> {
+        let info = match input.kind {
+            MediaKind::Audio(ref info) => info,
+            _ => return Err(Error::InvalidData),
+        };
+        if info.sample_rate != self.src.sample_rate
+            || info.map.len() != self.src.map.len()
+            || !is_f32_planar(&info.format)
+        {
+            return Err(Error::InvalidData);
+        }
+
+        let channels = info.map.len();
+        let samples = info.samples;
+        let buf: &dyn FrameBuffer = &*input.buf;
+
+        let mut inputs = Vec::with_capacity(channels);
+        for ch in 0..channels {
+            let plane: &[f32] = buf.as_slice(ch).map_err(|_| Error::InvalidData)?;
+            inputs.push(plane.get(..samples).ok_or(Error::InvalidData)?);
+        }
+
+        if samples == 0 {
+            return Ok(Frame::new_default_frame(
+                self.dst_info(0),
+                Some(input.t.clone()),
+            ));
+        }
+
+        let end = samples as u64 * self.den;
+        let out_len = if self.pos < end {
+            ((end - self.pos).div_ceil(self.step)) as usize
+        } else {
+            0
+        };
+
+        let mut out = Frame::new_default_frame(self.dst_info(out_len), Some(input.t.clone()));
+        let out_buf: &mut dyn FrameBuffer = &mut *out.buf;
+        let history = self
+            .history
+            .get_or_insert_with(|| inputs.iter().map(|s| s[0]).collect());
+
+        for (ch, src) in inputs.iter().enumerate() {
+            let dst: &mut [f32] = out_buf.as_mut_slice(ch).map_err(|_| Error::InvalidData)?;
+            // Index 0 is the last sample of the previous frame
+            let sample = |i: usize| if i == 0 { history[ch] } else { src[i - 1] };
+            let mut pos = self.pos;
+
+            for d in dst[..out_len].iter_mut() {
+                let i = (pos / self.den) as usize;
+                let frac = (pos % self.den) as f32 / self.den as f32;
+                *d = sample(i) * (1.0 - frac) + sample(i + 1) * frac;
+                pos += self.step;
+            }
+
+            history[ch] = src[samples - 1];
+        }
+
+        self.pos += out_len as u64 * self.step - end;
+
+        Ok(out)
+    }
+
+    fn dst_info(&self, samples: usize) -> AudioInfo {
+        AudioInfo {
+            samples,
+            sample_rate: self.dst.sample_rate,
+            map: self.dst.map.clone(),
+            format: Arc::clone(&self.dst.format),
+            block_len: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::audiosample::formats::{F32P, S16};
+    use crate::data::audiosample::ChannelMap;
+
+    fn info(samples: usize, sample_rate: usize, format: Soniton) -> AudioInfo {
+        AudioInfo::new(
+            samples,
+            sample_rate,
+            ChannelMap::default_map(2),
+            Arc::new(format),
+            None,
+        )
+    }
+
+    fn sine_frame(samples: usize, sample_rate: usize) -> Frame {
+        let mut frame = Frame::new_default_frame(info(samples, sample_rate, F32P), None);
+        let buf: &mut dyn FrameBuffer = &mut *frame.buf;
+
+        for ch in 0..2 {
+            let plane: &mut [f32] = buf.as_mut_slice(ch).unwrap();
+            for (i, s) in plane[..samples].iter_mut().enumerate() {
+                *s = (i as f32 * 440.0 * std::f32::consts::TAU / sample_rate as f32).sin();
+            }
+        }
+
+        frame
+    }
+
+    #[test]
+    fn downsample() {
+        let mut resampler = Resampler::new(info(0, 48000, F32P), info(0, 24000, F32P)).unwrap();
+        let input = sine_frame(960, 48000);
+
+        let mut total = 0;
+        for _ in 0..3 {
+            let out = resampler.process(&input).unwrap();
+            let out_info = out.kind.get_audio_info().unwrap();
+            assert_eq!(out_info.sample_rate, 24000);
+            assert_eq!(out_info.map.len(), 2);
+            total += out_info.samples;
+        }
+        assert_eq!(total, 960 * 3 / 2);
+
+        let mut resampler = Resampler::new(info(0, 48000, F32P), info(0, 24000, F32P)).unwrap();
+        let out = resampler.process(&input).unwrap();
+        let buf: &dyn FrameBuffer = &*out.buf;
+        let plane: &[f32] = buf.as_slice(1).unwrap();
+        let reference = sine_frame(480, 24000);
+        let ref_buf: &dyn FrameBuffer = &*reference.buf;
+        let ref_plane: &[f32] = ref_buf.as_slice(1).unwrap();
+
+        for (a, b) in plane[..480].iter().zip(&ref_plane[..480]) {
+            assert!((a - b).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(Resampler::new(info(0, 48000, S16), info(0, 24000, F32P)).is_err());
+
+        let mono = AudioInfo::new(0, 24000, ChannelMap::default_map(1), Arc::new(F32P), None);
+        assert!(Resampler::new(info(0, 48000, F32P), mono).is_err());
+    }
+}
@@ -1 +1,193 @@
+//! Video scaling.
+//!
+//! Only 8-bit planar formats are supported for now.
 
+use crate::data::frame::{Frame, MediaKind, VideoInfo};
+use crate::data::pixel::Formaton;
+
+pub use crate::error::{Error, Result};
+
+fn is_planar_8bit(format: &Formaton) -> bool {
+    format
+        .iter()
+        .flatten()
+        .all(|c| !c.is_packed() && c.get_depth() == 8)
+}
+
+/// Bilinear video scaler.
+///
+/// The pixel format is kept unchanged, only the frame size is.
+pub struct Scaler {
+    src: VideoInfo,
+    dst: VideoInfo,
+}
+
+impl Scaler {
+    /// Creates a new scaler from `src` to `dst` video.
+    ///
+    /// Both sides must use the same 8-bit planar pixel format.
+    pub fn new(src: VideoInfo, dst: VideoInfo) -> Result<Scaler> {
+        if *src.format != *dst.format {
+            return Err(Error::Unsupported("pixel format conversion".to_owned()));
+        }
+        if !is_planar_8bit(&src.format) {
+            return Err(Error::Unsupported("non 8-bit planar video".to_owned()));
+        }
+        if src.width == 0 || src.height == 0 || dst.width == 0 || dst.height == 0 {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(Scaler { src, dst })
+    }
+
+    /// Scales a video frame.
+    pub fn process(&mut self, input: &Frame) -> Result<Frame> {
+        match input.kind {
+            MediaKind::Video(ref info)
+                if info.width == self.src.width
+                    && info.height == self.src.height
+                    && *info.format == *self.src.format => {}
+            _ => return Err(Error::InvalidData),
+        }
+
+        let mut out = Frame::new_default_frame(self.dst.clone(), Some(input.t.clone()));
+
+        for c in self.src.format.iter().flatten() {
+            let idx = c.get_offset() as usize;
+            let sw = c.get_width(self.src.width);
+            let sh = c.get_height(self.src.height);
+            let dw = c.get_width(self.dst.width);
+            let dh = c.get_height(self.dst.height);
+            let src = input
+                .buf
+                .as_slice_inner(idx)
+                .map_err(|_| Error::InvalidData)?;
+            let src_linesize = input.buf.linesize(idx).map_err(|_| Error::InvalidData)?;
+            let dst_linesize = out.buf.linesize(idx).map_err(|_| Error::InvalidData)?;
+            let dst = out
+                .buf
+                .as_mut_slice_inner(idx)
+                .map_err(|_| Error::InvalidData)?;
+
+            scale_plane(dst, dst_linesize, dw, dh, src, src_linesize, sw, sh);
+        }
+
+        Ok(out)
+    }
+}
+
+// Maps a destination coordinate to the two nearest source coordinates
+// and the weight of the second one, aligning the sample centers.
+fn source_pos(d: usize, src_len: usize, dst_len: usize) -> (usize, usize, f32) {
+    let pos = ((d as f32 + 0.5) * src_len as f32 / dst_len as f32 - 0.5).max(0.0);
+    let p0 = (pos as usize).min(src_len - 1);
+    let p1 = (p0 + 1).min(src_len - 1);
+
+    (p0, p1, pos - p0 as f32)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn scale_plane(
+    dst: &mut [u8],
+    dst_linesize: usize,
+    dw: usize,
+    dh: usize,
+    src: &[u8],
+    src_linesize: usize,
+    sw: usize,
+    sh: usize,
+) {
+    if dw == 0 || sw == 0 || sh == 0 {
+        return;
+    }
+
+    let cols = (0..dw).map(|x| source_pos(x, sw, dw)).collect::<Vec<_>>();
+
+    for (y, line) in dst.chunks_mut(dst_linesize).take(dh).enumerate() {
+        let (y0, y1, fy) = source_pos(y, sh, dh);
+        let row0 = &src[y0 * src_linesize..];
+        let row1 = &src[y1 * src_linesize..];
+
+        for (d, &(x0, x1, fx)) in line[..dw].iter_mut().zip(cols.iter()) {
+            let top = row0[x0] as f32 * (1.0 - fx) + row0[x1] as f32 * fx;
+            let bottom = row1[x0] as f32 * (1.0 - fx) + row1[x1] as f32 * fx;
+            *d = (top * (1.0 - fy) + bottom * fy).round() as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::frame::FrameType;
+    use crate::data::pixel::formats::{RGB24, YUV420, YUV444};
+    use std::sync::Arc;
+
+    fn info(width: usize, height: usize, format: &Formaton) -> VideoInfo {
+        VideoInfo::new(width, height, false, FrameType::I, Arc::new(*format))
+    }
+
+    #[test]
+    fn upscale_solid() {
+        let values = [100, 50, 200];
+        let mut input = Frame::new_default_frame(info(16, 16, YUV420), None);
+        for (i, &v) in values.iter().enumerate() {
+            input.buf.as_mut_slice_inner(i).unwrap().fill(v);
+        }
+
+        let mut scaler = Scaler::new(info(16, 16, YUV420), info(32, 32, YUV420)).unwrap();
+        let out = scaler.process(&input).unwrap();
+
+        assert_eq!(out.kind.get_video_info().unwrap().width, 32);
+        assert_eq!(out.plane_to_vec(0).unwrap(), vec![100; 32 * 32]);
+        assert_eq!(out.plane_to_vec(1).unwrap(), vec![50; 16 * 16]);
+        assert_eq!(out.plane_to_vec(2).unwrap(), vec![200; 16 * 16]);
+    }
+
+    #[test]
+    fn downscale_gradient() {
+        let mut input = Frame::new_default_frame(info(4, 1, YUV444), None);
+        input.buf.as_mut_slice_inner(0).unwrap()[..4].copy_from_slice(&[0, 10, 20, 30]);
+
+        let mut scaler = Scaler::new(info(4, 1, YUV444), info(2, 1, YUV444)).unwrap();
+        let out = scaler.process(&input).unwrap();
+
+        assert_eq!(out.plane_to_vec(0).unwrap(), vec![5, 25]);
+    }
+
+    #[test]
+    fn plane_offsets() {
+        use crate::data::pixel::{Chromaton, ColorModel, TrichromaticEncodingSystem};
+
+        // The subsampled first component is stored in the second plane
+        let format = Formaton::new(
+            ColorModel::Trichromatic(TrichromaticEncodingSystem::RGB),
+            &[
+                Chromaton::yuv8(1, 1, 1),
+                Chromaton::yuv8(0, 0, 0),
+                Chromaton::yuv8(1, 1, 2),
+            ],
+            0,
+            false,
+            false,
+            false,
+        );
+        let mut input = Frame::new_default_frame(info(16, 16, &format), None);
+        for (i, v) in [100, 50, 200].into_iter().enumerate() {
+            input.buf.as_mut_slice_inner(i).unwrap().fill(v);
+        }
+
+        let mut scaler = Scaler::new(info(16, 16, &format), info(32, 32, &format)).unwrap();
+        let out = scaler.process(&input).unwrap();
+
+        assert_eq!(out.plane_to_vec(0).unwrap(), vec![100; 32 * 32]);
+        assert_eq!(out.plane_to_vec(1).unwrap(), vec![50; 16 * 16]);
+        assert_eq!(out.plane_to_vec(2).unwrap(), vec![200; 16 * 16]);
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(Scaler::new(info(16, 16, YUV420), info(32, 32, YUV444)).is_err());
+        assert!(Scaler::new(info(16, 16, RGB24), info(32, 32, RGB24)).is_err());
+    }
+}